@@ -3,6 +3,7 @@ use bevy_sequence::prelude::*;
 use criterion::{criterion_group, criterion_main, Criterion};
 use std::hint::black_box;
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 struct Dialogue(&'static str);
 
@@ -31,6 +32,15 @@ fn nested() -> impl IntoFragment<Dialogue> {
         .always()
 }
 
+fn evaluated() -> impl IntoFragment<Dialogue> {
+    (
+        "Hello, Alice!".eval(|| true),
+        select(("Hey Bob...", "Hey Alice..."), || 0),
+        "Crazy weather we're having, huh?".eval_id(|_: In<FragmentId>| true),
+    )
+        .always()
+}
+
 impl IntoFragment<Dialogue> for &'static str {
     fn into_fragment(self, context: &Context, commands: &mut Commands) -> FragmentId {
         <_ as IntoFragment<Dialogue>>::into_fragment(
@@ -46,7 +56,7 @@ fn ping_pong(
     mut writer: EventWriter<FragmentEndEvent>,
 ) {
     for event in reader.read() {
-        writer.send(event.end());
+        writer.write(event.end());
     }
 }
 
@@ -72,6 +82,8 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("selection control", |b| {
         let mut app = App::new();
         app.add_plugins(MinimalPlugins)
+            .add_event::<FragmentEvent<Dialogue>>()
+            .add_event::<FragmentEndEvent>()
             .add_systems(Update, ping_pong)
             .add_systems(Startup, |mut commands: Commands| {
                 spawn_root(scene(), &mut commands);
@@ -124,6 +136,61 @@ fn criterion_benchmark(c: &mut Criterion) {
             app.update();
         })
     });
+
+    c.bench_function("selection thousand evaluated", |b| {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, SequencePlugin))
+            .add_systems(Update, ping_pong)
+            .add_systems(Startup, |mut commands: Commands| {
+                for _ in 0..1000 {
+                    spawn_root(evaluated(), &mut commands);
+                }
+            });
+
+        b.iter(|| {
+            app.update();
+        })
+    });
+
+    // Trees that never play, so nothing in them changes between frames.
+    c.bench_function("selection thousand idle", |b| {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, SequencePlugin))
+            .add_systems(Update, ping_pong)
+            .add_systems(Startup, |mut commands: Commands| {
+                for _ in 0..1000 {
+                    spawn_root(nested().eval(|| false), &mut commands);
+                }
+            });
+
+        b.iter(|| {
+            app.update();
+        })
+    });
+
+    // Scores are only compared within each tree, so every tree plays,
+    // and only the trees that changed are walked again.
+    for (name, walk) in [
+        ("selection thousand scored", TreeWalk::Incremental),
+        ("selection thousand scored full walk", TreeWalk::Full),
+    ] {
+        c.bench_function(name, |b| {
+            let mut app = App::new();
+            app.add_plugins((MinimalPlugins, SequencePlugin))
+                .insert_resource(SelectionMode::Score)
+                .insert_resource(walk)
+                .add_systems(Update, ping_pong)
+                .add_systems(Startup, |mut commands: Commands| {
+                    for i in 0..1000 {
+                        spawn_root(nested().eval(move || i as f32), &mut commands);
+                    }
+                });
+
+            b.iter(|| {
+                app.update();
+            })
+        });
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -46,6 +46,6 @@ fn ping_pong(
 ) {
     for event in reader.read() {
         println!("{}", &event.data.0);
-        writer.send(event.end());
+        writer.write(event.end());
     }
 }
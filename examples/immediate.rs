@@ -13,6 +13,7 @@ fn main() {
         .run();
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 struct Dialogue(&'static str);
 
@@ -28,7 +29,7 @@ fn scene() -> impl IntoFragment<Dialogue> {
 }
 
 impl IntoFragment<Dialogue> for &'static str {
-    fn into_fragment(self, context: &(), commands: &mut Commands) -> FragmentId {
+    fn into_fragment(self, context: &Context, commands: &mut Commands) -> FragmentId {
         <_ as IntoFragment<Dialogue>>::into_fragment(
             bevy_sequence::fragment::DataLeaf::new(Dialogue(self)),
             context,
@@ -43,6 +44,6 @@ fn ping_pong(
 ) {
     for event in reader.read() {
         // println!("{}", &event.data.0);
-        writer.send(event.end());
+        writer.write(event.end());
     }
 }
@@ -47,7 +47,7 @@ fn shopkeep() -> impl IntoFragment<Dialogue> {
 }
 
 impl IntoFragment<Dialogue> for &'static str {
-    fn into_fragment(self, context: &(), commands: &mut Commands) -> FragmentId {
+    fn into_fragment(self, context: &Context, commands: &mut Commands) -> FragmentId {
         <_ as IntoFragment<Dialogue>>::into_fragment(
             bevy_sequence::fragment::DataLeaf::new(Dialogue(self.into())),
             context,
@@ -62,7 +62,7 @@ fn ping_pong(
 ) {
     for event in reader.read() {
         println!("{}", &event.data.0);
-        writer.send(event.end());
+        writer.write(event.end());
     }
 }
 
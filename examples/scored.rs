@@ -0,0 +1,56 @@
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_sequence::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default(), SequencePlugin))
+        .insert_resource(SelectionMode::Score)
+        .insert_resource(Health(0.25))
+        .add_systems(Startup, |mut commands: Commands| {
+            info!("Starting up");
+
+            // Every bark in the tree is a candidate, but only the highest-scoring one is selected.
+            spawn_root(
+                best_of((
+                    "I could use a drink.".eval(|| 0.5),
+                    "I need a healer!".eval(|health: Res<Health>| 1.0 - health.0),
+                    "Is that all you've got?"
+                        .eval(|health: Res<Health>| (health.0 > 0.5, health.0)),
+                ))
+                .always(),
+                &mut commands,
+            );
+        })
+        .add_systems(Update, (ping_pong, heal))
+        .run();
+}
+
+#[derive(Resource)]
+struct Health(f32);
+
+fn heal(mut health: ResMut<Health>) {
+    health.0 = (health.0 + 0.05).min(1.0);
+}
+
+#[derive(Debug, Clone)]
+struct Dialogue(String);
+
+impl IntoFragment<Dialogue> for &'static str {
+    fn into_fragment(self, context: &Context, commands: &mut Commands) -> FragmentId {
+        <_ as IntoFragment<Dialogue>>::into_fragment(
+            bevy_sequence::fragment::DataLeaf::new(Dialogue(self.into())),
+            context,
+            commands,
+        )
+    }
+}
+
+fn ping_pong(
+    mut reader: EventReader<FragmentEvent<Dialogue>>,
+    mut writer: EventWriter<FragmentEndEvent>,
+) {
+    for event in reader.read() {
+        println!("{}", &event.data.0);
+        writer.write(event.end());
+    }
+}
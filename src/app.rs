@@ -45,9 +45,27 @@ pub enum SequenceSets {
     Save,
 }
 
+/// Stages within [SequenceSets::Evaluate].
+///
+/// Fragments are first prepared for evaluation, like resetting limits.
+/// Then, evaluations that come from the structure of a tree, like a
+/// sequence's current item or an exhausted limit, are applied so that
+/// fragment systems can be skipped for unreachable fragments.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum EvaluateSets {
+    Prepare,
+    Structure,
+    Reachability,
+    Systems,
+}
+
 impl Plugin for SequencePlugin {
     fn build(&self, app: &mut App) {
         let world = app.world_mut();
+        world.register_component::<ChildOf>();
+        world.register_component::<fragment::Root>();
+        world.register_component::<fragment::Reachable>();
+        world.register_component::<fragment::SettledEvaluation>();
         world.register_component::<OnBeginUp>();
         world.register_component::<OnBeginDown>();
         world.register_component::<OnEndUp>();
@@ -58,12 +76,25 @@ impl Plugin for SequencePlugin {
 
         app.add_plugins(CombinatorPlugin)
             .insert_resource(AddedSystems(Default::default()))
+            .init_resource::<PendingSystems>()
             .insert_resource(fragment::SelectedFragments::default())
+            .insert_resource(fragment::SelectionMode::default())
+            .insert_resource(fragment::TreeWalk::default())
+            .init_resource::<crate::clock::SequenceClock>()
             .add_event::<FragmentEndEvent>()
+            .add_systems(First, insert_pending_systems)
             .add_systems(
                 PreUpdate,
                 (
-                    crate::fragment::select_fragments.in_set(SequenceSets::Select),
+                    crate::clock::tick_clock.before(SequenceSets::Evaluate),
+                    fragment::update_reachable.in_set(EvaluateSets::Reachability),
+                    (
+                        fragment::settle_evals,
+                        fragment::mark_dirty_roots,
+                        fragment::select_fragments,
+                    )
+                        .chain()
+                        .in_set(SequenceSets::Select),
                     ApplyDeferred
                         .after(SequenceSets::Evaluate)
                         .before(SequenceSets::Select),
@@ -81,6 +112,14 @@ impl Plugin for SequencePlugin {
             .configure_sets(
                 PreUpdate,
                 (
+                    (
+                        EvaluateSets::Prepare,
+                        EvaluateSets::Structure,
+                        EvaluateSets::Reachability,
+                        EvaluateSets::Systems,
+                    )
+                        .chain()
+                        .in_set(SequenceSets::Evaluate),
                     SequenceSets::Select.after(SequenceSets::Evaluate),
                     SequenceSets::Emit.after(SequenceSets::Select),
                 ),
@@ -98,6 +137,10 @@ struct AddedSystems(HashSet<TypeId>);
 /// Insert systems into a schedule.
 ///
 /// This will only insert a set of systems into a given schedule once.
+///
+/// Configured systems, like `my_system.in_set(MySet)`, all share
+/// the same type, so only the first would ever be inserted.
+/// Use [add_systems_checked_in] to insert systems into a set.
 pub fn add_systems_checked<M, S, C>(world: &mut World, schedule: S, systems: C)
 where
     S: ScheduleLabel,
@@ -112,6 +155,67 @@ where
     }
 }
 
+/// Insert systems into a set within a schedule.
+///
+/// This will only insert a set of systems into a given schedule once.
+pub fn add_systems_checked_in<M, S, T, C>(world: &mut World, schedule: S, set: T, systems: C)
+where
+    S: ScheduleLabel + Clone,
+    T: SystemSet + Clone,
+    C: IntoScheduleConfigs<ScheduleSystem, M> + Send + Sync + 'static,
+{
+    let id = TypeId::of::<(S, T, C)>();
+    let mut pairs = world.get_resource_or_insert_with(AddedSystems::default);
+
+    if pairs.0.insert(id) {
+        insert_systems(world, schedule, set, systems);
+    }
+}
+
+/// Systems that couldn't be inserted because their schedule was running.
+#[derive(Resource, Default)]
+struct PendingSystems(Vec<Box<dyn FnOnce(&mut Schedules) + Send + Sync>>);
+
+/// Insert systems into a set within a schedule.
+///
+/// A schedule is removed from the world while it runs, and any systems
+/// added in the meantime would be lost. In that case, insertion is
+/// deferred to the start of the next frame.
+pub(crate) fn insert_systems<M, S, T, C>(world: &mut World, schedule: S, set: T, systems: C)
+where
+    S: ScheduleLabel + Clone,
+    T: SystemSet + Clone,
+    C: IntoScheduleConfigs<ScheduleSystem, M> + Send + Sync + 'static,
+{
+    let mut schedules = world.resource_mut::<Schedules>();
+    if schedules.contains(schedule.clone()) {
+        schedules.add_systems(schedule, systems.in_set(set));
+    } else {
+        world
+            .get_resource_or_insert_with(PendingSystems::default)
+            .0
+            .push(Box::new(move |schedules: &mut Schedules| {
+                schedules.add_systems(schedule, systems.in_set(set));
+            }));
+    }
+}
+
+fn insert_pending_systems(world: &mut World) {
+    let Some(mut pending) = world.get_resource_mut::<PendingSystems>() else {
+        return;
+    };
+
+    if pending.0.is_empty() {
+        return;
+    }
+
+    let pending = core::mem::take(&mut pending.0);
+    let mut schedules = world.resource_mut::<Schedules>();
+    for insert in pending {
+        insert(&mut schedules);
+    }
+}
+
 pub trait AddSystemsChecked: Sized {
     /// Queues inserting systems into a schedule.
     ///
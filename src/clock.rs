@@ -0,0 +1,49 @@
+use bevy_ecs::prelude::*;
+use bevy_time::Time;
+use std::time::Duration;
+
+/// The clock used by time-based combinators, like cooldowns.
+///
+/// The clock advances with [Time], but can be paused
+/// independently to stop every sequence timer at once.
+///
+/// [`FragmentState`](crate::fragment::FragmentState) timestamps are
+/// measured with this clock, so it should be saved and restored
+/// alongside [`SavedSequences`](crate::combinators::save::SavedSequences).
+#[derive(Debug, Default, Clone, Resource)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequenceClock {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl SequenceClock {
+    /// The total time elapsed while the clock was running.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Set the elapsed time, like when restoring a saved clock.
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+pub(crate) fn tick_clock(mut clock: ResMut<SequenceClock>, time: Res<Time>) {
+    if !clock.paused {
+        clock.elapsed += time.delta();
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{fragment::children::IntoChildren, prelude::*};
+use bevy_ecs::prelude::*;
+
+/// A fragment whose children are all candidates at once.
+pub struct BestOfFragment<F> {
+    fragments: F,
+}
+
+/// A fragment whose children are all candidates at once.
+///
+/// Every child that can play is considered, and the [SelectionMode]
+/// picks the best of them. With [`SelectionMode::Score`], the
+/// highest-scoring child plays. Since scores are only compared within
+/// a tree, this is how separate candidates compete with each other.
+/// ```ignore
+/// best_of((
+///     "I could use a drink.".eval(|| 0.5),
+///     "I need a healer!".eval(|health: Res<Health>| 1.0 - health.0),
+/// ))
+/// ```
+pub fn best_of<F>(fragments: F) -> BestOfFragment<F> {
+    BestOfFragment { fragments }
+}
+
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct BestOf;
+
+impl<D, C, F> IntoFragment<D, C> for BestOfFragment<F>
+where
+    D: Threaded,
+    F: IntoChildren<D, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let children = self.fragments.into_children(context, commands);
+
+        FragmentId::new(commands.spawn(BestOf).add_children(children.as_ref()).id())
+    }
+}
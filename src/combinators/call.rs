@@ -0,0 +1,214 @@
+use crate::fragment::Reachable;
+use crate::prelude::*;
+use bevy_ecs::{entity::EntityHashSet, prelude::*, system::SystemParam};
+use bevy_log::prelude::*;
+use bevy_platform::collections::{HashMap, HashSet};
+use std::{
+    borrow::Cow,
+    sync::{Arc, RwLock},
+};
+
+type Builder = Arc<dyn Fn(&mut Commands) -> FragmentId + Send + Sync>;
+
+/// Named sequences that can be shared between trees with [call].
+///
+/// Each sequence is spawned once, the first time it's called,
+/// and is reused by every tree that calls it.
+/// ```ignore
+/// fn setup(mut registry: ResMut<SequenceRegistry>) {
+///     registry.register::<Dialogue, _>("shop_menu", shop_menu);
+/// }
+/// ```
+#[derive(Default, Resource)]
+pub struct SequenceRegistry {
+    builders: HashMap<Cow<'static, str>, Builder>,
+    spawned: HashMap<Cow<'static, str>, Entity>,
+}
+
+impl SequenceRegistry {
+    /// Register a sequence's builder with a name.
+    ///
+    /// Registering a name again replaces its builder,
+    /// but a sequence that is already spawned is kept.
+    pub fn register<Data, F>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        builder: impl Fn() -> F + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        Data: Threaded,
+        F: IntoFragment<Data>,
+    {
+        self.builders.insert(
+            name.into(),
+            Arc::new(move |commands| builder().into_fragment(&Arc::new(RwLock::new(())), commands)),
+        );
+        self
+    }
+
+    /// The spawned sequence for a name, if it has been called.
+    pub fn get(&self, name: &str) -> Option<FragmentId> {
+        self.spawned.get(name).copied().map(FragmentId::new)
+    }
+}
+
+/// A fragment that plays a sequence from the [SequenceRegistry].
+pub struct CallFragment {
+    name: Cow<'static, str>,
+}
+
+/// A fragment that plays a sequence from the [SequenceRegistry].
+///
+/// While the call is reached, the named sequence is attached as its
+/// child and plays as part of the calling tree. The call ends once
+/// the sequence ends, so the calling tree continues from there.
+/// ```ignore
+/// (
+///     "Looking to buy something?",
+///     call("shop_menu"),
+///     "Thanks for stopping by!",
+/// )
+/// ```
+///
+/// A sequence can only be attached to one call at a time. Other calls
+/// wait until the sequence is idle and its current call can no longer
+/// be reached. A sequence that calls itself, directly or through other
+/// sequences, is reported as an error and never plays.
+///
+/// The sequence's state is shared between every call and isn't saved
+/// with the calling tree. Use `save_as` in the sequence's builder to persist it.
+pub fn call(name: impl Into<Cow<'static, str>>) -> CallFragment {
+    CallFragment { name: name.into() }
+}
+
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct Call {
+    name: Cow<'static, str>,
+}
+
+impl Call {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<Data, C> IntoFragment<Data, C> for CallFragment
+where
+    Data: Threaded,
+{
+    fn into_fragment(self, _: &Context<C>, commands: &mut Commands) -> FragmentId {
+        FragmentId::new(commands.spawn(Call { name: self.name }).id())
+    }
+}
+
+/// The parts of the tree [update_calls] reads.
+#[derive(SystemParam)]
+pub(super) struct CallTree<'w, 's> {
+    names: Query<'w, 's, &'static Call>,
+    sequences: Query<'w, 's, (&'static FragmentState, Option<&'static ChildOf>)>,
+    reachable: Query<'w, 's, &'static Reachable>,
+    parents: Query<'w, 's, &'static ChildOf>,
+}
+
+impl CallTree<'_, '_> {
+    /// Whether a call is within a sequence of the same name,
+    /// walking up through every call above it.
+    fn calls_itself(&self, call: Entity, name: &str) -> bool {
+        let mut node = call;
+        while let Ok(parent) = self.parents.get(node) {
+            node = parent.parent();
+            if self.names.get(node).is_ok_and(|call| call.name == name) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// State kept by [update_calls] between frames.
+#[derive(Default)]
+pub(super) struct CallScratch {
+    /// Sequences attached to a call this frame.
+    claimed: EntityHashSet,
+    /// Calls already reported as calling themselves.
+    cycles: EntityHashSet,
+    /// Names already reported as unregistered.
+    unregistered: HashSet<Cow<'static, str>>,
+}
+
+/// Attach each called sequence to a reachable call, spawning it if needed.
+///
+/// Reachability is from the previous frame, since it's
+/// only known once the tree's structure is evaluated.
+pub(super) fn update_calls(
+    mut calls: Query<(Entity, &Call, &Reachable, &mut Evaluation)>,
+    tree: CallTree,
+    mut registry: ResMut<SequenceRegistry>,
+    mut commands: Commands,
+    mut scratch: Local<CallScratch>,
+) {
+    let CallScratch {
+        claimed,
+        cycles,
+        unregistered,
+    } = &mut *scratch;
+    claimed.clear();
+
+    for (entity, call, call_reachable, mut eval) in calls.iter_mut() {
+        if !call_reachable.0 {
+            continue;
+        }
+
+        // checked before spawning, so a cycle never spawns another copy of itself
+        if tree.calls_itself(entity, &call.name) {
+            if cycles.insert(entity) {
+                error!("sequence \"{}\" calls itself", call.name);
+            }
+
+            eval.merge(false.evaluate());
+            continue;
+        }
+
+        if let Some(sequence) = registry.spawned.get(&call.name)
+            && claimed.contains(sequence)
+        {
+            continue;
+        }
+
+        let sequence = match registry.spawned.get(&call.name) {
+            Some(sequence) if tree.sequences.contains(*sequence) => *sequence,
+            _ => {
+                let Some(builder) = registry.builders.get(&call.name).cloned() else {
+                    if unregistered.insert(call.name.clone()) {
+                        warn!("no sequence registered as \"{}\"", call.name);
+                    }
+                    continue;
+                };
+
+                let sequence = builder(&mut commands).entity();
+                registry.spawned.insert(call.name.clone(), sequence);
+                commands.entity(entity).add_child(sequence);
+                claimed.insert(sequence);
+
+                continue;
+            }
+        };
+
+        let Ok((state, owner)) = tree.sequences.get(sequence) else {
+            continue;
+        };
+        let owner = owner.map(|o| o.parent());
+        if owner == Some(entity) || state.active || !state.active_events.is_empty() {
+            continue;
+        }
+
+        let owner_reachable =
+            owner.is_some_and(|owner| tree.reachable.get(owner).is_ok_and(|r| r.0));
+        if !owner_reachable {
+            commands.entity(entity).add_child(sequence);
+            claimed.insert(sequence);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use super::evaluated::EvalSystems;
+use crate::fragment::systems::{insert_fragment_system, FragmentSystemKind};
+use crate::{fragment::Reachable, prelude::*};
+use bevy_ecs::prelude::*;
+use std::marker::PhantomData;
+
+/// A fragment that plays the branch of the first true condition.
+pub struct CondFragment<B, M> {
+    branches: B,
+    _marker: PhantomData<fn() -> M>,
+}
+
+/// A fragment that plays the branch of the first true condition.
+///
+/// Each branch pairs a condition with a fragment. Conditions can be
+/// any system accepted by [`FragmentExt::eval`], and are checked in
+/// order every frame. Only the first branch whose condition is true
+/// is selected, even if that branch has nothing left to play.
+///
+/// Once a branch begins, it's kept until it ends,
+/// and conditions aren't checked in the meantime.
+/// ```ignore
+/// cond((
+///     (is_night, night_lines()),
+///     (is_raining, rain_lines()),
+///     (otherwise, default_lines()),
+/// ))
+/// ```
+pub fn cond<B, M>(branches: B) -> CondFragment<B, M> {
+    CondFragment {
+        branches,
+        _marker: PhantomData,
+    }
+}
+
+/// A condition that is always true, for the last branch of a [cond].
+pub fn otherwise() -> bool {
+    true
+}
+
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct Cond;
+
+/// Wraps a branch's fragment so the branch's
+/// [Evaluation] only reflects its condition.
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct CondBranch;
+
+/// Merges a branch's condition into its [Evaluation],
+/// except while the branch is playing.
+pub(super) struct CondSystems;
+
+impl FragmentSystemKind<Evaluation> for CondSystems {
+    type Gate = (&'static FragmentState, &'static Reachable);
+
+    fn should_run((state, reachable): (&FragmentState, &Reachable)) -> bool {
+        reachable.0 && !state.active
+    }
+
+    fn apply(world: &mut World, fragment: FragmentId, output: Evaluation) {
+        EvalSystems::apply(world, fragment, output);
+    }
+}
+
+/// A collection of condition and fragment pairs.
+pub trait IntoBranches<Data: Threaded, C, M> {
+    type Collection: AsRef<[Entity]>;
+
+    fn into_branches(self, context: &Context<C>, commands: &mut Commands) -> Self::Collection;
+}
+
+fn branch<Data, C, S, F, O, M>(
+    condition: S,
+    fragment: F,
+    context: &Context<C>,
+    commands: &mut Commands,
+) -> Entity
+where
+    Data: Threaded,
+    F: IntoFragment<Data, C>,
+    S: IntoSystem<(), O, M> + Send + 'static,
+    O: Evaluate + 'static,
+{
+    let fragment = fragment.into_fragment(context, commands);
+    let branch = commands.spawn(CondBranch).add_child(fragment.entity()).id();
+
+    insert_fragment_system::<CondSystems, _, _, _, _>(
+        commands,
+        FragmentId::new(branch),
+        condition.map(|input: O| input.evaluate()),
+    );
+
+    branch
+}
+
+macro_rules! cond_branches {
+    ($count:literal, $(($S:ident, $F:ident, $O:ident, $M:ident)),*) => {
+        #[allow(non_snake_case)]
+        impl<Data, C, $($S, $F, $O, $M),*> IntoBranches<Data, C, ($(($O, $M),)*)> for ($(($S, $F),)*)
+        where
+            Data: Threaded,
+            $(
+                $F: IntoFragment<Data, C>,
+                $S: IntoSystem<(), $O, $M> + Send + 'static,
+                $O: Evaluate + 'static,
+            )*
+        {
+            type Collection = [Entity; $count];
+
+            fn into_branches(self, context: &Context<C>, commands: &mut Commands) -> Self::Collection {
+                let ($(($S, $F),)*) = self;
+                [$(branch::<Data, C, $S, $F, $O, $M>($S, $F, context, commands)),*]
+            }
+        }
+    }
+}
+
+variadics_please::all_tuples_with_size!(cond_branches, 1, 15, S, F, O, M);
+
+impl<Data, C, B, M> IntoFragment<Data, C> for CondFragment<B, M>
+where
+    Data: Threaded,
+    B: IntoBranches<Data, C, M>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let branches = self.branches.into_branches(context, commands);
+
+        FragmentId::new(commands.spawn(Cond).add_children(branches.as_ref()).id())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    clock::SequenceClock,
+    fragment::event::{EndStage, InsertEndDown},
+    prelude::*,
+};
+use bevy_ecs::prelude::*;
+use std::time::Duration;
+
+/// Evaluate false for some time after the fragment ends.
+pub struct Cooldown<T> {
+    fragment: T,
+    duration: Duration,
+}
+
+impl<T> Cooldown<T> {
+    pub fn new(fragment: T, duration: Duration) -> Self {
+        Self { fragment, duration }
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct CooldownItem {
+    duration: Duration,
+    ready_at: Option<Duration>,
+}
+
+impl CooldownItem {
+    /// The time left before this fragment can be selected again.
+    pub fn remaining(&self, clock: &SequenceClock) -> Duration {
+        self.ready_at
+            .map(|ready| ready.saturating_sub(clock.elapsed()))
+            .unwrap_or_default()
+    }
+
+    pub(super) fn set_remaining(&mut self, remaining: Duration, clock: &SequenceClock) {
+        self.ready_at = (!remaining.is_zero()).then(|| clock.elapsed() + remaining);
+    }
+}
+
+impl<T, C, D> IntoFragment<D, C> for Cooldown<T>
+where
+    T: IntoFragment<D, C>,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        let entity = id.entity();
+
+        commands
+            .entity(entity)
+            .insert(CooldownItem {
+                duration: self.duration,
+                ready_at: None,
+            })
+            .insert_end_down(move |stage, world| {
+                if matches!(stage.stage, EndStage::End) {
+                    let now = world.resource::<SequenceClock>().elapsed();
+                    if let Some(mut cooldown) = world.get_mut::<CooldownItem>(entity) {
+                        cooldown.ready_at = Some(now + cooldown.duration);
+                    }
+                }
+            });
+
+        id
+    }
+}
+
+pub(super) fn evaluate_cooldowns(
+    mut fragments: Query<(&mut Evaluation, &CooldownItem)>,
+    clock: Res<SequenceClock>,
+) {
+    for (mut eval, cooldown) in fragments.iter_mut() {
+        if cooldown
+            .ready_at
+            .is_some_and(|ready| ready > clock.elapsed())
+        {
+            eval.merge(false.evaluate());
+        }
+    }
+}
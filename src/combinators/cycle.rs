@@ -0,0 +1,57 @@
+use crate::{fragment::children::IntoChildren, prelude::*};
+use bevy_ecs::prelude::*;
+
+/// A fragment that plays the next of its children each time it's triggered.
+pub struct CycleFragment<F> {
+    fragments: F,
+}
+
+/// A fragment that plays the next of its children each time it's triggered,
+/// wrapping back around to the first.
+///
+/// The current child is determined by [FragmentState::triggered],
+/// so the position is persisted with `save_as`.
+pub fn cycle<F>(fragments: F) -> CycleFragment<F> {
+    CycleFragment { fragments }
+}
+
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct Cycle;
+
+impl<D, C, F> IntoFragment<D, C> for CycleFragment<F>
+where
+    D: Threaded,
+    F: IntoChildren<D, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let children = self.fragments.into_children(context, commands);
+
+        FragmentId::new(commands.spawn(Cycle).add_children(children.as_ref()).id())
+    }
+}
+
+pub(super) fn update_cycle_items(
+    cycles: Query<(&Children, &FragmentState), With<Cycle>>,
+    mut children_query: Query<&mut Evaluation>,
+) {
+    for (children, state) in cycles.iter() {
+        if children.is_empty() {
+            continue;
+        }
+
+        // `triggered` is incremented as soon as the cycle starts
+        let played = if state.active {
+            state.triggered.saturating_sub(1)
+        } else {
+            state.triggered
+        };
+        let current = played % children.len();
+
+        for (i, child) in children.iter().enumerate() {
+            if let Ok(mut evaluation) = children_query.get_mut(child) {
+                evaluation.merge((current == i).evaluate());
+            }
+        }
+    }
+}
@@ -1,23 +1,50 @@
-use crate::{app::AddSystemsChecked, fragment::children::IntoChildren, prelude::*};
+use crate::{
+    app::{add_systems_checked_in, EvaluateSets},
+    fragment::{
+        children::IntoChildren,
+        event::{BeginStage, InsertBeginDown},
+        systems::{insert_fragment_system, FragmentSystemKind},
+        Reachable,
+    },
+    prelude::*,
+};
 use bevy_app::PreUpdate;
-use bevy_ecs::prelude::*;
-use rand::distributions::{uniform::SampleUniform, WeightedIndex};
+use bevy_ecs::{entity::EntityHashMap, prelude::*, system::EntityCommands};
+use bevy_log::prelude::*;
+use rand::distr::{
+    uniform::SampleUniform,
+    weighted::{Weight, WeightedIndex},
+    Distribution as _, Uniform,
+};
+use std::{collections::VecDeque, marker::PhantomData};
 
 /// A fragment that randomly selects its children.
 ///
 /// Equivalent to [DistributionFragment] where all weights are equal.
 pub struct ChoiceFragment<F> {
     fragments: F,
+    avoid_recent: usize,
 }
 
 /// A fragment that randomly selects its children.
 ///
 /// Equivalent to [DistributionFragment] where all weights are equal.
 pub fn choice<F>(fragments: F) -> ChoiceFragment<F> {
-    ChoiceFragment { fragments }
+    ChoiceFragment {
+        fragments,
+        avoid_recent: 0,
+    }
 }
 
-pub fn test() {}
+impl<F> ChoiceFragment<F> {
+    /// Avoid selecting any of the last `n` picks.
+    ///
+    /// If every child was picked recently, the recent picks are ignored.
+    pub fn avoid_recent(mut self, n: usize) -> Self {
+        self.avoid_recent = n;
+        self
+    }
+}
 
 impl<D, C, F> IntoFragment<D, C> for ChoiceFragment<F>
 where
@@ -26,23 +53,19 @@ where
 {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
         let children = self.fragments.into_children(context, commands);
-        commands.add_systems_checked(
-            PreUpdate,
-            test.in_set(SequenceSets::Evaluate),
-        );
-
-        let mut entity = commands.spawn((Fragment, DistributionActiveNode(0)));
+        commands.queue(|world: &mut World| {
+            add_systems_checked_in(
+                world,
+                PreUpdate,
+                EvaluateSets::Structure,
+                update_distribution_items::<usize>,
+            );
+        });
 
+        let distribution = Distribution::new(children.as_ref().iter().map(|_| 1usize));
+        let mut entity = commands.spawn((DistributionActiveNode(None), distribution));
         entity.add_children(children.as_ref());
-
-        match WeightedIndex::new(children.as_ref().iter().map(|_| 1)) {
-            Ok(distribution) => {
-                entity.insert(Distribution(distribution));
-            }
-            Err(e) => {
-                bevy_log::error!("unable to spawn choice fragment: {e}");
-            }
-        }
+        insert_recent_picks(&mut entity, self.avoid_recent);
 
         FragmentId::new(entity.id())
     }
@@ -52,9 +75,13 @@ where
 pub struct DistributionFragment<F, D, const LENGTH: usize> {
     fragments: F,
     distribution: [D; LENGTH],
+    avoid_recent: usize,
 }
 
 /// A fragment that selects its children based on a probability distribution.
+///
+/// Children with zero, negative or non-finite weights won't be selected.
+/// If the weights' total overflows, no child is selected.
 pub fn distribution<F, D, const LENGTH: usize>(
     fragments: F,
     distribution: [D; LENGTH],
@@ -62,15 +89,251 @@ pub fn distribution<F, D, const LENGTH: usize>(
     DistributionFragment {
         fragments,
         distribution,
+        avoid_recent: 0,
+    }
+}
+
+impl<F, D, const LENGTH: usize> DistributionFragment<F, D, LENGTH> {
+    /// Avoid selecting any of the last `n` picks.
+    ///
+    /// If every child with a valid weight was picked recently,
+    /// the recent picks are ignored.
+    pub fn avoid_recent(mut self, n: usize) -> Self {
+        self.avoid_recent = n;
+        self
     }
 }
 
 #[derive(Clone, Copy, Component)]
 #[require(Fragment)]
-pub(super) struct DistributionActiveNode(usize);
+pub(super) struct DistributionActiveNode(Option<usize>);
 
+/// A fixed distribution.
 #[derive(Component)]
-pub(super) struct Distribution<X: SampleUniform + PartialOrd>(WeightedIndex<X>);
+pub(super) struct Distribution<X: SampleUniform + PartialOrd> {
+    weights: Vec<X>,
+    /// `None` if no child has a valid weight.
+    index: Option<WeightedIndex<X>>,
+}
+
+impl<X> Distribution<X>
+where
+    X: SampleUniform + PartialOrd + Weight,
+{
+    fn new(weights: impl IntoIterator<Item = X>) -> Self {
+        let weights: Vec<_> = weights.into_iter().collect();
+
+        Self {
+            index: weighted_index(weights.iter().cloned()),
+            weights,
+        }
+    }
+
+    fn sample(&self, recent: Option<&RecentPicks>) -> Option<usize> {
+        match recent {
+            Some(recent) if !recent.picks.is_empty() => sample_weights(&self.weights, Some(recent)),
+            _ => self.index.as_ref().map(|d| d.sample(&mut rand::rng())),
+        }
+    }
+}
+
+/// The most recent picks of a distribution, newest last.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecentPicks {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    capacity: usize,
+    picks: VecDeque<usize>,
+}
+
+impl RecentPicks {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            picks: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Whether the child at `index` was picked recently.
+    pub fn contains(&self, index: usize) -> bool {
+        self.picks.contains(&index)
+    }
+
+    fn push(&mut self, index: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.picks.len() == self.capacity {
+            self.picks.pop_front();
+        }
+        self.picks.push_back(index);
+    }
+}
+
+/// The recent picks of every distribution in a root's tree.
+///
+/// Picks are kept by the root rather than the distribution, so a
+/// distribution in a sequence shared with [`call`] avoids repeats
+/// for each calling tree separately. They're saved along with the
+/// rest of the tree's state by `save_as`.
+///
+/// [`call`]: crate::combinators::call::call
+#[derive(Debug, Default, Component)]
+pub struct RecentMemory(EntityHashMap<RecentPicks>);
+
+impl RecentMemory {
+    /// The recent picks of a distribution in this root's tree.
+    pub fn get(&self, distribution: FragmentId) -> Option<&RecentPicks> {
+        self.0.get(&distribution.entity())
+    }
+}
+
+/// How many picks a distribution avoids repeating.
+#[derive(Debug, Component)]
+pub(super) struct AvoidRecent {
+    capacity: usize,
+    /// Picks restored from a save, until the distribution's root remembers its own.
+    loaded: Option<RecentPicks>,
+}
+
+impl AvoidRecent {
+    /// Restore saved picks, keeping only the newest that fit.
+    pub(super) fn load(&mut self, saved: &RecentPicks) {
+        let mut picks = RecentPicks::new(self.capacity);
+        picks.picks.clone_from(&saved.picks);
+        while picks.picks.len() > self.capacity {
+            picks.picks.pop_front();
+        }
+
+        self.loaded = Some(picks);
+    }
+
+    /// The distribution's recent picks within the tree of `memory`'s root.
+    pub(super) fn picks<'a>(
+        &'a self,
+        distribution: Entity,
+        memory: Option<&'a RecentMemory>,
+    ) -> Option<&'a RecentPicks> {
+        memory
+            .and_then(|memory| memory.0.get(&distribution))
+            .or(self.loaded.as_ref())
+    }
+}
+
+/// The root of the tree `fragment` is currently in.
+pub(super) fn tree_root(mut fragment: Entity, parent: impl Fn(Entity) -> Option<Entity>) -> Entity {
+    while let Some(next) = parent(fragment) {
+        fragment = next;
+    }
+
+    fragment
+}
+
+/// Remember a distribution's pick in its root's [RecentMemory] when it starts.
+fn insert_recent_picks(entity: &mut EntityCommands, n: usize) {
+    if n == 0 {
+        return;
+    }
+
+    let id = entity.id();
+    entity
+        .insert(AvoidRecent {
+            capacity: n,
+            loaded: None,
+        })
+        .insert_begin_down(move |stage, world| {
+            if !matches!(stage.stage, BeginStage::Start) {
+                return;
+            }
+
+            let Some(DistributionActiveNode(Some(pick))) = world.get(id).copied() else {
+                return;
+            };
+            let Some(mut avoid) = world.get_mut::<AvoidRecent>(id) else {
+                return;
+            };
+            let capacity = avoid.capacity;
+            let loaded = avoid.loaded.take();
+
+            let root = tree_root(id, |e| world.get::<ChildOf>(e).map(ChildOf::parent));
+            let mut root = world.entity_mut(root);
+            if !root.contains::<RecentMemory>() {
+                root.insert(RecentMemory::default());
+            }
+
+            if let Some(mut memory) = root.get_mut::<RecentMemory>() {
+                memory
+                    .0
+                    .entry(id)
+                    .or_insert_with(|| loaded.unwrap_or_else(|| RecentPicks::new(capacity)))
+                    .push(pick);
+            }
+        });
+}
+
+/// Sample an index from `weights`, avoiding recent picks
+/// unless every valid child was picked recently.
+fn sample_weights<X>(weights: &[X], recent: Option<&RecentPicks>) -> Option<usize>
+where
+    X: SampleUniform + PartialOrd + Weight,
+{
+    let avoiding = recent.and_then(|recent| {
+        weighted_index(weights.iter().enumerate().map(|(i, w)| {
+            if recent.contains(i) {
+                X::ZERO
+            } else {
+                w.clone()
+            }
+        }))
+    });
+
+    avoiding
+        .or_else(|| weighted_index(weights.iter().cloned()))
+        .map(|d| d.sample(&mut rand::rng()))
+}
+
+/// Build a weighted index, treating invalid weights as zero.
+///
+/// A weight is invalid if it's not greater than zero or, for floats, not finite.
+/// Returns `None` if no weight is valid or if the weights' total overflows.
+fn weighted_index<X>(weights: impl IntoIterator<Item = X>) -> Option<WeightedIndex<X>>
+where
+    X: SampleUniform + PartialOrd + Weight,
+{
+    // `Uniform` rejects empty and non-finite ranges, which is exactly what
+    // `WeightedIndex` would otherwise panic on when sampling its total.
+    let valid = |w: &X| Uniform::new(X::ZERO, w.clone()).is_ok();
+
+    let weights: Vec<X> = weights
+        .into_iter()
+        .map(|w| if valid(&w) { w } else { X::ZERO })
+        .collect();
+
+    let mut total = X::ZERO;
+    for weight in &weights {
+        total.checked_add_assign(weight).ok()?;
+    }
+    if !valid(&total) {
+        return None;
+    }
+
+    WeightedIndex::new(weights).ok()
+}
+
+/// Merge each child's evaluation with whether it was selected.
+fn merge_selection(
+    selection: Option<usize>,
+    children: &Children,
+    evaluations: &mut Query<&mut Evaluation>,
+) {
+    for (i, child) in children.iter().enumerate() {
+        if let Ok(mut evaluation) = evaluations.get_mut(child) {
+            evaluation.merge((selection == Some(i)).evaluate());
+        }
+    }
+}
 
 macro_rules! distribution_implementation {
     ($count:literal, $($ty:ident),*) => {
@@ -78,28 +341,26 @@ macro_rules! distribution_implementation {
         impl<C, Data, D, $($ty),*> IntoFragment<Data, C> for DistributionFragment<($($ty,)*), D, $count>
         where
             Data: Threaded,
-            D: SampleUniform + PartialOrd + for<'a> std::ops::AddAssign<&'a D> + Clone + Default + Threaded,
+            D: SampleUniform + PartialOrd + Weight + Threaded,
             D::Sampler: Threaded,
             $($ty: IntoFragment<Data, C>),*
         {
             fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
                 let ($($ty,)*) = self.fragments;
-                let children = [$($ty.into_fragment(context, commands) .entity()),*];
-                commands.add_systems_checked(PreUpdate, update_distribution_items::<D>.in_set(SequenceSets::Evaluate));
-
-                let mut entity = commands
-                    .spawn(DistributionActiveNode(0));
+                let children = [$($ty.into_fragment(context, commands).entity()),*];
+                commands.queue(|world: &mut World| {
+                    add_systems_checked_in(
+                        world,
+                        PreUpdate,
+                        EvaluateSets::Structure,
+                        update_distribution_items::<D>,
+                    );
+                });
 
+                let distribution = Distribution::new(self.distribution);
+                let mut entity = commands.spawn((DistributionActiveNode(None), distribution));
                 entity.add_children(&children);
-
-                match WeightedIndex::new(self.distribution){
-                    Ok(distribution)=> {
-                        entity.insert(Distribution(distribution));
-                    }
-                    Err(e)=> {
-                        bevy_log::error!("unable to spawn distribution fragment: {e}");
-                    }
-                }
+                insert_recent_picks(&mut entity, self.avoid_recent);
 
                 FragmentId::new(entity.id())
             }
@@ -111,34 +372,180 @@ variadics_please::all_tuples_with_size!(distribution_implementation, 1, 15, T);
 
 pub(super) fn update_distribution_items<X>(
     mut choices: Query<(
+        Entity,
         &Children,
         &FragmentState,
         &Distribution<X>,
         &mut DistributionActiveNode,
+        Option<&AvoidRecent>,
     )>,
     mut children_query: Query<&mut Evaluation>,
+    parents: Query<&ChildOf>,
+    memories: Query<&RecentMemory>,
 ) where
-    X: SampleUniform + PartialOrd + Threaded,
+    X: SampleUniform + PartialOrd + Weight + Threaded,
     X::Sampler: Threaded,
 {
-    use rand::prelude::*;
+    for (entity, children, state, distribution, mut active, avoid) in choices.iter_mut() {
+        if !state.active {
+            let recent = avoid.and_then(|avoid| {
+                let root = tree_root(entity, |e| parents.get(e).ok().map(ChildOf::parent));
+                avoid.picks(entity, memories.get(root).ok())
+            });
+            active.0 = distribution.sample(recent);
+        }
+
+        merge_selection(active.0, children, &mut children_query);
+    }
+}
+
+/// A fragment that selects its children based on weights computed by a system.
+pub struct DynamicDistributionFragment<F, S, O, M> {
+    fragments: F,
+    system: S,
+    avoid_recent: usize,
+    _marker: PhantomData<fn() -> (O, M)>,
+}
+
+/// A fragment that selects its children based on weights computed by a system.
+///
+/// The system is run each time a selection is made and should return a weight
+/// for each child. Children with zero, negative or non-finite weights won't be
+/// selected. If the number of weights doesn't match the number of children,
+/// a warning is logged, extra weights are ignored and missing weights are zero.
+/// If the weights add up to more than `f32::MAX`, no child is selected.
+/// ```ignore
+/// distribution_with(
+///     ("Nice sword.", "Nice bow."),
+///     |weapon: Res<Weapon>| match *weapon {
+///         Weapon::Sword => [3.0, 1.0],
+///         Weapon::Bow => [1.0, 3.0],
+///     },
+/// )
+/// ```
+pub fn distribution_with<F, S, O, M>(
+    fragments: F,
+    system: S,
+) -> DynamicDistributionFragment<F, S, O, M>
+where
+    S: IntoSystem<(), O, M>,
+    O: IntoIterator<Item = f32>,
+{
+    DynamicDistributionFragment {
+        fragments,
+        system,
+        avoid_recent: 0,
+        _marker: PhantomData,
+    }
+}
 
-    let mut rng = rand::thread_rng();
+impl<F, S, O, M> DynamicDistributionFragment<F, S, O, M> {
+    /// Avoid selecting any of the last `n` picks.
+    ///
+    /// If every child with a valid weight was picked recently,
+    /// the recent picks are ignored.
+    pub fn avoid_recent(mut self, n: usize) -> Self {
+        self.avoid_recent = n;
+        self
+    }
+}
+
+/// Marks a distribution with weights computed by a system.
+#[derive(Default, Component)]
+pub(super) struct DynamicDistribution {
+    /// Whether a mismatched number of weights has been reported.
+    warned: bool,
+}
+
+/// Samples a distribution from its system's weights,
+/// rerunning the system only when the distribution is inactive and [Reachable].
+pub(super) struct DistributionWeights;
 
-    for (children, state, distribution, mut active) in choices.iter_mut() {
-        let selection = if !state.active {
-            let selection = distribution.0.sample(&mut rng);
-            active.0 = selection;
-            selection
-        } else {
-            active.0
+impl FragmentSystemKind<Vec<f32>> for DistributionWeights {
+    type Gate = (&'static FragmentState, &'static Reachable);
+
+    fn should_run((state, reachable): (&FragmentState, &Reachable)) -> bool {
+        !state.active && reachable.0
+    }
+
+    fn apply(world: &mut World, fragment: FragmentId, mut weights: Vec<f32>) {
+        let Ok(mut entity) = world.get_entity_mut(fragment.entity()) else {
+            return;
         };
 
-        for (i, child) in children.iter().enumerate() {
-            let Ok(mut evaluation) = children_query.get_mut(child) else {
-                continue;
-            };
-            evaluation.merge((selection == i).evaluate());
+        let children = entity
+            .get::<Children>()
+            .map(|c| c.to_vec())
+            .unwrap_or_default();
+        if weights.len() != children.len()
+            && let Some(mut distribution) = entity.get_mut::<DynamicDistribution>()
+            && !distribution.warned
+        {
+            warn!(
+                "`distribution_with` returned {} weights for {} children",
+                weights.len(),
+                children.len()
+            );
+            distribution.warned = true;
+        }
+
+        weights.resize(children.len(), 0.0);
+
+        let id = fragment.entity();
+        let root = tree_root(id, |e| world.get::<ChildOf>(e).map(ChildOf::parent));
+        let recent = world
+            .get::<AvoidRecent>(id)
+            .and_then(|avoid| avoid.picks(id, world.get::<RecentMemory>(root)));
+        let selection = sample_weights(&weights, recent);
+        world
+            .entity_mut(id)
+            .insert(DistributionActiveNode(selection));
+
+        for (i, child) in children.into_iter().enumerate() {
+            if let Some(mut evaluation) = world.get_mut::<Evaluation>(child) {
+                evaluation.merge((selection == Some(i)).evaluate());
+            }
+        }
+    }
+}
+
+impl<D, C, F, S, O, M> IntoFragment<D, C> for DynamicDistributionFragment<F, S, O, M>
+where
+    D: Threaded,
+    F: IntoChildren<D, C>,
+    S: IntoSystem<(), O, M> + Send + 'static,
+    O: IntoIterator<Item = f32> + 'static,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let children = self.fragments.into_children(context, commands);
+
+        let mut entity =
+            commands.spawn((DistributionActiveNode(None), DynamicDistribution::default()));
+        entity.add_children(children.as_ref());
+        insert_recent_picks(&mut entity, self.avoid_recent);
+        let id = FragmentId::new(entity.id());
+
+        insert_fragment_system::<DistributionWeights, _, _, _, _>(
+            commands,
+            id,
+            self.system
+                .map(|weights: O| weights.into_iter().collect::<Vec<_>>()),
+        );
+
+        id
+    }
+}
+
+/// Keep evaluating the chosen child while a dynamic distribution is active.
+///
+/// Inactive distributions are sampled in [DistributionWeights].
+pub(super) fn update_dynamic_distribution_items(
+    choices: Query<(&Children, &FragmentState, &DistributionActiveNode), With<DynamicDistribution>>,
+    mut children_query: Query<&mut Evaluation>,
+) {
+    for (children, state, active) in choices.iter() {
+        if state.active {
+            merge_selection(active.0, children, &mut children_query);
         }
     }
 }
@@ -1,7 +1,7 @@
+use crate::fragment::systems::{insert_fragment_system, FragmentSystemKind};
+use crate::fragment::Reachable;
 use crate::prelude::*;
-use bevy_ecs::component::{Mutable, StorageType};
 use bevy_ecs::prelude::*;
-use bevy_ecs::system::SystemId;
 use std::marker::PhantomData;
 
 pub struct EvaluatedWithId<F, T, O, M> {
@@ -20,56 +20,44 @@ impl<F, T, O, M> EvaluatedWithId<F, T, O, M> {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct EvalSystemId(SystemId<In<FragmentId>, Evaluation>);
+/// Merges a fragment system's output into the fragment's [Evaluation].
+///
+/// Systems are only run for [Reachable] fragments.
+pub(super) struct EvalSystems;
 
-// Here we automatically clean up the system when this component is removed.
-impl Component for EvalSystemId {
-    const STORAGE_TYPE: StorageType = StorageType::Table;
+impl FragmentSystemKind<Evaluation> for EvalSystems {
+    type Gate = &'static Reachable;
 
-    type Mutability = Mutable;
+    fn should_run(reachable: &Reachable) -> bool {
+        reachable.0
+    }
 
-    fn register_component_hooks(hooks: &mut bevy_ecs::component::ComponentHooks) {
-        hooks.on_remove(|mut world, ctx| {
-            let eval = world.get::<EvalSystemId>(ctx.entity).unwrap().0;
-            world.commands().unregister_system(eval);
-        });
+    fn apply(world: &mut World, fragment: FragmentId, output: Evaluation) {
+        if let Some(mut evaluation) = world.get_mut::<Evaluation>(fragment.entity()) {
+            evaluation.merge(output);
+        }
     }
 }
 
 impl<C, Data, F, T, O, M> IntoFragment<Data, C> for EvaluatedWithId<F, T, O, M>
 where
     F: IntoFragment<Data, C>,
-    T: IntoSystem<In<FragmentId>, O, M> + 'static,
+    T: IntoSystem<In<FragmentId>, O, M> + Send + 'static,
     O: Evaluate + 'static,
     Data: Threaded,
 {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
         let id = self.fragment.into_fragment(context, commands);
-        let system = commands.register_system(self.evaluation.map(|input: O| input.evaluate()));
-
-        commands.entity(id.entity()).insert(EvalSystemId(system));
+        insert_fragment_system::<EvalSystems, _, _, _, _>(
+            commands,
+            id,
+            self.evaluation.map(|input: O| input.evaluate()),
+        );
 
         id
     }
 }
 
-pub(super) fn custom_evals_ids(
-    systems: Query<(Entity, &EvalSystemId), With<Evaluation>>,
-    mut commands: Commands,
-) {
-    let systems: Vec<_> = systems.iter().map(|(e, s)| (e, *s)).collect();
-
-    commands.queue(|world: &mut World| {
-        for (e, system) in systems {
-            let evaluation = world.run_system_with(system.0, FragmentId::new(e)).unwrap();
-            let mut entity_eval = world.entity_mut(e);
-            let mut entity_eval = entity_eval.get_mut::<Evaluation>().unwrap();
-            entity_eval.merge(evaluation);
-        }
-    });
-}
-
 pub struct Evaluated<F, T, O, M> {
     pub(super) fragment: F,
     pub(super) evaluation: T,
@@ -86,52 +74,21 @@ impl<F, T, O, M> Evaluated<F, T, O, M> {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct EvalSystem(SystemId<(), Evaluation>);
-
-// Here we automatically clean up the system when this component is removed.
-impl Component for EvalSystem {
-    const STORAGE_TYPE: StorageType = StorageType::Table;
-
-    type Mutability = Mutable;
-
-    fn register_component_hooks(hooks: &mut bevy_ecs::component::ComponentHooks) {
-        hooks.on_remove(|mut world, ctx| {
-            let eval = world.get::<EvalSystemId>(ctx.entity).unwrap().0;
-            world.commands().unregister_system(eval);
-        });
-    }
-}
-
 impl<C, Data, F, T, O, M> IntoFragment<Data, C> for Evaluated<F, T, O, M>
 where
     F: IntoFragment<Data, C>,
-    T: IntoSystem<(), O, M> + 'static,
+    T: IntoSystem<(), O, M> + Send + 'static,
     O: Evaluate + 'static,
     Data: Threaded,
 {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
         let id = self.fragment.into_fragment(context, commands);
-        let system = commands.register_system(self.evaluation.map(|input: O| input.evaluate()));
-
-        commands.entity(id.entity()).insert(EvalSystem(system));
+        insert_fragment_system::<EvalSystems, _, _, _, _>(
+            commands,
+            id,
+            self.evaluation.map(|input: O| input.evaluate()),
+        );
 
         id
     }
 }
-
-pub(super) fn custom_evals(
-    systems: Query<(Entity, &EvalSystem), With<Evaluation>>,
-    mut commands: Commands,
-) {
-    let systems: Vec<_> = systems.iter().map(|(e, s)| (e, *s)).collect();
-
-    commands.queue(|world: &mut World| {
-        for (e, system) in systems {
-            let evaluation = world.run_system(system.0).unwrap();
-            let mut entity_eval = world.entity_mut(e);
-            let mut entity_eval = entity_eval.get_mut::<Evaluation>().unwrap();
-            entity_eval.merge(evaluation);
-        }
-    });
-}
@@ -0,0 +1,50 @@
+use crate::{fragment::children::IntoChildren, prelude::*};
+use bevy_ecs::prelude::*;
+
+/// A fragment that plays the first of its children with anything to play.
+pub struct FirstAvailableFragment<F> {
+    fragments: F,
+}
+
+/// A fragment that plays the first of its children with anything to play.
+///
+/// Children are tried in order, and only the first child whose subtree
+/// evaluates to at least one leaf is selected. Lower priority children
+/// are never considered while a higher priority child is available.
+/// ```ignore
+/// first_available((
+///     "You found the key!".eval(has_key),
+///     "Still looking?".limit(2),
+///     "Keep searching.",
+/// ))
+/// ```
+///
+/// While a child is active, it remains selected until it ends.
+/// The selected leaves keep their own evaluations, so their counts
+/// compare against the rest of the tree as usual. Nothing is stored
+/// on the fragment itself, so the choice is rebuilt from the
+/// children's saved state when loaded with `save_as`.
+pub fn first_available<F>(fragments: F) -> FirstAvailableFragment<F> {
+    FirstAvailableFragment { fragments }
+}
+
+#[derive(Debug, Component)]
+#[require(Fragment)]
+pub struct FirstAvailable;
+
+impl<D, C, F> IntoFragment<D, C> for FirstAvailableFragment<F>
+where
+    D: Threaded,
+    F: IntoChildren<D, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let children = self.fragments.into_children(context, commands);
+
+        FragmentId::new(
+            commands
+                .spawn(FirstAvailable)
+                .add_children(children.as_ref())
+                .id(),
+        )
+    }
+}
@@ -0,0 +1,339 @@
+use super::sequence::SequenceCursor;
+use crate::fragment::event::{
+    end_recursive, unwind_node, BeginStage, EndStage, InsertBeginDown, StageEvent,
+};
+use crate::fragment::{Leaf, Root};
+use crate::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use std::borrow::Cow;
+
+/// Name a fragment so [goto] can jump to it.
+pub struct Labeled<T> {
+    fragment: T,
+    label: Cow<'static, str>,
+}
+
+impl<T> Labeled<T> {
+    pub fn new(fragment: T, label: Cow<'static, str>) -> Self {
+        Self { fragment, label }
+    }
+}
+
+/// A fragment's label.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub struct Label(pub Cow<'static, str>);
+
+impl<T, C, D> IntoFragment<D, C> for Labeled<T>
+where
+    T: IntoFragment<D, C>,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        commands.entity(id.entity()).insert(Label(self.label));
+
+        id
+    }
+}
+
+/// A fragment that jumps to a labelled fragment in the same tree.
+pub struct GotoFragment {
+    label: Cow<'static, str>,
+}
+
+/// A fragment that jumps to a labelled fragment in the same tree.
+///
+/// When the goto is played, the labelled fragment plays next from its start.
+/// ```ignore
+/// (
+///     "Welcome to my shop!",
+///     (
+///         "What can I do for you?",
+///         select_by(choice, (
+///             (Choice::Browse, ("Take a look.", goto("menu"))),
+///             (Choice::Leave, "Come again!"),
+///         )),
+///     )
+///         .label("menu"),
+/// )
+/// ```
+///
+/// Every active fragment between the goto and the target is unwound
+/// and counted as interrupted, running its interrupt and end hooks,
+/// along with anything else still playing within them.
+/// Sequences on the way to the target are moved to it, skipping the
+/// items before it and rewinding the items after it, and sequences
+/// within the target are rewound so the target begins a fresh pass.
+/// Only the sequences' cursors are moved; the fragments' states keep
+/// counting everything that was played.
+///
+/// If no fragment in the tree has the label, the goto just ends.
+pub fn goto(label: impl Into<Cow<'static, str>>) -> GotoFragment {
+    GotoFragment {
+        label: label.into(),
+    }
+}
+
+#[derive(Debug, Component)]
+#[require(Leaf)]
+pub struct Goto(pub Cow<'static, str>);
+
+impl<Data, C> IntoFragment<Data, C> for GotoFragment
+where
+    Data: Threaded,
+{
+    fn into_fragment(self, _: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let mut goto = commands.spawn(Goto(self.label.clone()));
+
+        let entity = goto.id();
+        goto.insert_begin_down(move |event, world| {
+            jump(world, entity, event, &self.label);
+        });
+
+        FragmentId::new(entity)
+    }
+}
+
+/// A fragment followed by each of its ancestors, up to its root.
+///
+/// Returns `None` if any of them no longer exists.
+fn ancestors(world: &World, fragment: Entity) -> Option<Vec<Entity>> {
+    let mut path = vec![fragment];
+    let mut node = world.get_entity(fragment).ok()?;
+
+    while !node.contains::<Root>()
+        && let Some(parent) = node.get::<ChildOf>()
+    {
+        node = world.get_entity(parent.parent()).ok()?;
+        path.push(node.id());
+    }
+
+    Some(path)
+}
+
+/// Find the first fragment with `label`, depth-first.
+fn find_label(world: &World, node: Entity, label: &str) -> Option<Entity> {
+    if world.get::<Label>(node).is_some_and(|l| l.0 == label) {
+        return Some(node);
+    }
+
+    world
+        .get::<Children>(node)?
+        .iter()
+        .find_map(|child| find_label(world, child, label))
+}
+
+fn children(world: &World, fragment: Entity) -> Vec<Entity> {
+    world
+        .get::<Children>(fragment)
+        .map(|c| c.to_vec())
+        .unwrap_or_default()
+}
+
+/// The states of a fragment's children, along with its own completion count.
+fn child_states(world: &World, fragment: Entity) -> (Vec<Option<FragmentState>>, usize) {
+    let states = children(world, fragment)
+        .into_iter()
+        .map(|child| world.get::<FragmentState>(child).cloned())
+        .collect();
+    let completed = world
+        .get::<FragmentState>(fragment)
+        .map_or(0, |state| state.completed);
+
+    (states, completed)
+}
+
+/// Unwind every active fragment in this subtree, deepest first.
+fn unwind_subtree(world: &mut World, fragment: Entity) {
+    for child in children(world, fragment) {
+        unwind_subtree(world, child);
+    }
+
+    if world
+        .get::<FragmentState>(fragment)
+        .is_some_and(|state| state.active)
+    {
+        unwind_node(fragment, world);
+    }
+
+    if let Some(mut state) = world.get_mut::<FragmentState>(fragment) {
+        state.active_events = Default::default();
+    }
+}
+
+/// Rewind every sequence in this subtree to the start of its current pass.
+fn rewind(world: &mut World, fragment: Entity) {
+    let (states, completed) = child_states(world, fragment);
+    if let Some(mut cursor) = world.get_mut::<SequenceCursor>(fragment) {
+        cursor.catch_up(states.iter().map(Option::as_ref), completed);
+        for (i, state) in states.iter().enumerate() {
+            if let Some(state) = state {
+                cursor.rewind(i, state, completed);
+            }
+        }
+    }
+
+    for child in children(world, fragment) {
+        rewind(world, child);
+    }
+}
+
+/// Move `parent` to `child`, skipping the items before
+/// it and rewinding the rest.
+fn move_to(world: &mut World, parent: Entity, child: Entity) {
+    let items = children(world, parent);
+    let Some(position) = items.iter().position(|item| *item == child) else {
+        return;
+    };
+
+    let (states, completed) = child_states(world, parent);
+    let Some(mut cursor) = world.get_mut::<SequenceCursor>(parent) else {
+        rewind(world, child);
+        return;
+    };
+
+    cursor.catch_up(states.iter().map(Option::as_ref), completed);
+    for (i, state) in states.iter().enumerate() {
+        let Some(state) = state else {
+            continue;
+        };
+
+        if i < position {
+            if !cursor.is_done(i, state, completed) {
+                cursor.skip(i);
+            }
+        } else {
+            cursor.rewind(i, state, completed);
+        }
+    }
+
+    for item in &items[position..] {
+        rewind(world, *item);
+    }
+}
+
+fn jump(world: &mut World, goto: Entity, event: StageEvent<BeginStage>, label: &str) {
+    let path = ancestors(world, goto);
+    let target = path
+        .as_ref()
+        .and_then(|path| find_label(world, path[path.len() - 1], label));
+
+    let (Some(path), Some(target)) = (path, target) else {
+        warn!("no fragment labelled \"{label}\" found for `goto`");
+        end_recursive(
+            goto,
+            None,
+            StageEvent {
+                id: event.id,
+                stage: EndStage::End,
+            },
+            world,
+        );
+        return;
+    };
+
+    // the goto ends immediately without ending anything above it
+    end_recursive(
+        goto,
+        None,
+        StageEvent {
+            id: event.id,
+            stage: EndStage::Visit,
+        },
+        world,
+    );
+
+    let now = world.get_resource::<SequenceClock>().map(|c| c.elapsed());
+    if let Some(mut state) = world.get_mut::<FragmentState>(goto) {
+        state.completed += 1;
+        state.active = false;
+        state.last_end = now;
+    }
+
+    let Some(target_path) = ancestors(world, target) else {
+        return;
+    };
+    let Some(common) = path.iter().position(|node| target_path.contains(node)) else {
+        return;
+    };
+
+    // the target is restarted as well when the goto is inside it
+    let unwound = if path[common] == target {
+        &path[1..=common]
+    } else {
+        &path[1..common]
+    };
+
+    if let Some((top, above)) = unwound.last().zip(path.get(unwound.len() + 1..)) {
+        // anything still playing in the abandoned pass is no longer
+        // playing as far as the fragments above it are concerned
+        let abandoned = world
+            .get::<FragmentState>(*top)
+            .map(|state| state.active_events.to_vec())
+            .unwrap_or_default();
+        unwind_subtree(world, *top);
+
+        for node in above {
+            if let Some(mut state) = world.get_mut::<FragmentState>(*node) {
+                for event in &abandoned {
+                    state.active_events.remove(*event);
+                }
+            }
+        }
+    }
+
+    let common = target_path
+        .iter()
+        .position(|node| *node == path[common])
+        .unwrap_or_default();
+    for step in target_path[..=common].windows(2).rev() {
+        move_to(world, step[1], step[0]);
+    }
+
+    rewind(world, target);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::test_app::{app, run, spawn};
+
+    #[test]
+    fn goto_leaves_a_nested_sequence() {
+        let mut app = app();
+        spawn(
+            &mut app,
+            (
+                "start",
+                ("a", ("b", goto("end"), "unreached"), "also unreached"),
+                "skipped",
+                "last".label("end"),
+            )
+                .always()
+                .once(),
+        );
+
+        assert_eq!(run(&mut app, 20), ["start", "a", "b", "last"]);
+    }
+
+    #[test]
+    fn goto_loops_back_out_of_a_nested_sequence() {
+        let mut app = app();
+        spawn(
+            &mut app,
+            (
+                "a".label("top"),
+                (
+                    "b",
+                    goto("top").limit(1).mode(LimitMode::Triggers).optional(),
+                ),
+                "c",
+            )
+                .always()
+                .once(),
+        );
+
+        assert_eq!(run(&mut app, 20), ["a", "b", "a", "b", "c"]);
+    }
+}
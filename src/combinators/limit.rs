@@ -1,20 +1,91 @@
-use crate::prelude::*;
+use crate::fragment::event::{BeginStage, EndStage, InsertBeginDown, InsertEndDown};
+use crate::fragment::systems::{insert_fragment_system, FragmentSystemKind};
+use crate::{app::EvaluateSets, prelude::*};
 use bevy_ecs::prelude::*;
+use std::marker::PhantomData;
 
 /// A wrapper fragment that limits its children to a certain number of executions.
 pub struct Limit<T> {
     fragment: T,
     limit: usize,
+    mode: LimitMode,
 }
 
 impl<T> Limit<T> {
     pub fn new(fragment: T, limit: usize) -> Self {
-        Self { fragment, limit }
+        Self {
+            fragment,
+            limit,
+            mode: LimitMode::default(),
+        }
+    }
+
+    /// Set what this limit counts.
+    pub fn mode(mut self, mode: LimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Reset this limit's count whenever `system` returns true.
+    ///
+    /// The system runs every frame, even while the count is zero.
+    ///
+    /// ```ignore
+    /// "Morning!".once().reset_when(resource_changed::<Day>)
+    /// ```
+    pub fn reset_when<S, M>(self, system: S) -> ResetWhen<T, S, M>
+    where
+        S: IntoSystem<(), bool, M> + Send + 'static,
+    {
+        ResetWhen {
+            limit: self,
+            system,
+            _marker: PhantomData,
+        }
     }
 }
 
+/// What a [Limit] counts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LimitMode {
+    /// Count each time the fragment starts.
+    Triggers,
+
+    /// Count each time the fragment ends.
+    #[default]
+    Completions,
+
+    /// Count each time the fragment is interrupted.
+    Interruptions,
+}
+
 #[derive(Debug, Component)]
-pub struct LimitItem(usize);
+pub struct LimitItem {
+    limit: usize,
+    mode: LimitMode,
+    count: usize,
+}
+
+impl LimitItem {
+    /// The number of times this limit has been counted since it was last reset.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+
+    /// Restore a saved count, falling back to the fragment's
+    /// state for saves that don't include one.
+    pub(super) fn load(&mut self, count: Option<usize>, state: &FragmentState) {
+        self.count = count.unwrap_or(match self.mode {
+            LimitMode::Triggers => state.triggered,
+            LimitMode::Completions => state.completed,
+            LimitMode::Interruptions => state.interrupted,
+        });
+    }
+}
 
 impl<T, C, D> IntoFragment<D, C> for Limit<T>
 where
@@ -23,15 +94,101 @@ where
 {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
         let id = self.fragment.into_fragment(context, commands);
-        commands.entity(id.entity()).insert(LimitItem(self.limit));
+        let entity = id.entity();
+
+        let mut commands = commands.entity(entity);
+        commands.insert(LimitItem {
+            limit: self.limit,
+            mode: self.mode,
+            count: 0,
+        });
+
+        let increment = move |world: &mut World| {
+            if let Some(mut limit) = world.get_mut::<LimitItem>(entity) {
+                limit.count += 1;
+            }
+        };
+
+        match self.mode {
+            LimitMode::Triggers => {
+                commands.insert_begin_down(move |stage, world| {
+                    if matches!(stage.stage, BeginStage::Start) {
+                        increment(world);
+                    }
+                });
+            }
+            LimitMode::Completions | LimitMode::Interruptions => {
+                let counted = match self.mode {
+                    LimitMode::Completions => EndStage::End,
+                    _ => EndStage::Interrupt,
+                };
+
+                commands.insert_end_down(move |stage, world| {
+                    if stage.stage == counted {
+                        increment(world);
+                    }
+                });
+            }
+        }
 
         id
     }
 }
 
-pub(super) fn evaluate_limits(mut fragments: Query<(&mut Evaluation, &FragmentState, &LimitItem)>) {
-    for (mut eval, state, limit) in fragments.iter_mut() {
-        if state.completed >= limit.0 {
+/// A [Limit] that is reset whenever a system returns true.
+pub struct ResetWhen<T, S, M> {
+    limit: Limit<T>,
+    system: S,
+    _marker: PhantomData<fn() -> M>,
+}
+
+/// Resets a fragment's limit when its system returns true.
+pub(super) struct LimitResets;
+
+impl FragmentSystemKind<bool> for LimitResets {
+    type Gate = &'static LimitItem;
+
+    const SET: EvaluateSets = EvaluateSets::Prepare;
+
+    // run every frame, so change detection in the
+    // system never sees a stale last run
+    fn should_run(_: &LimitItem) -> bool {
+        true
+    }
+
+    fn apply(world: &mut World, fragment: FragmentId, reset: bool) {
+        if reset && let Some(mut limit) = world.get_mut::<LimitItem>(fragment.entity()) {
+            limit.reset();
+        }
+    }
+}
+
+impl<T, S, M, C, D> IntoFragment<D, C> for ResetWhen<T, S, M>
+where
+    T: IntoFragment<D, C>,
+    S: IntoSystem<(), bool, M> + Send + 'static,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.limit.into_fragment(context, commands);
+        insert_fragment_system::<LimitResets, _, _, _, _>(commands, id, self.system);
+
+        id
+    }
+}
+
+/// Reset a fragment's limit.
+pub fn reset_limit(fragment: FragmentId, commands: &mut Commands) {
+    commands.queue(move |world: &mut World| {
+        if let Some(mut limit) = world.get_mut::<LimitItem>(fragment.entity()) {
+            limit.reset();
+        }
+    });
+}
+
+pub(super) fn evaluate_limits(mut fragments: Query<(&mut Evaluation, &LimitItem)>) {
+    for (mut eval, limit) in fragments.iter_mut() {
+        if limit.count >= limit.limit {
             eval.merge(false.evaluate());
         }
     }
@@ -3,24 +3,39 @@ use bevy_ecs::prelude::*;
 use std::{borrow::Cow, time::Duration};
 
 pub mod always;
+pub mod best_of;
+pub mod call;
+pub mod cond;
+pub mod cooldown;
+pub mod cycle;
 pub mod delay;
-//pub mod distribution;
+pub mod distribution;
 pub mod evaluated;
+pub mod first_available;
 pub mod hooks;
+pub mod label;
 pub mod limit;
+pub mod on_event;
 pub mod or;
 pub mod save;
 pub mod select;
 pub mod sequence;
+pub mod shuffle;
+pub mod signal;
+pub mod state_machine;
+pub mod timeline;
 
 pub use always::AlwaysFragment;
+pub use cooldown::Cooldown;
 pub use delay::Delay;
 pub use evaluated::{Evaluated, EvaluatedWithId};
 pub use hooks::{OnEnd, OnInterrupt, OnStart, OnVisit};
-pub use limit::Limit;
+pub use label::Labeled;
+pub use limit::{reset_limit, Limit, LimitMode};
+pub use on_event::OnEvent;
 pub use or::Or;
 pub use save::Save;
-pub use sequence::Sequence;
+pub use sequence::{Optional, Overlap, Sequence};
 
 use crate::prelude::{Evaluate, FragmentId};
 
@@ -29,17 +44,29 @@ pub struct CombinatorPlugin;
 impl Plugin for CombinatorPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(save::SavedSequences::default())
+            .init_resource::<call::SequenceRegistry>()
+            .init_resource::<signal::Signals>()
+            .add_systems(
+                PreUpdate,
+                call::update_calls.in_set(crate::app::EvaluateSets::Prepare),
+            )
             .add_systems(
                 PreUpdate,
                 (
                     sequence::update_sequence_items,
-                    evaluated::custom_evals,
-                    evaluated::custom_evals_ids,
                     limit::evaluate_limits,
                     select::update_select_items,
                     always::evaluate_always,
+                    cooldown::evaluate_cooldowns,
+                    shuffle::update_shuffle_items,
+                    timeline::update_timeline_tracks,
+                    state_machine::update_state_machines,
+                    signal::evaluate_signals,
+                    signal::update_barriers,
+                    cycle::update_cycle_items,
+                    distribution::update_dynamic_distribution_items,
                 )
-                    .in_set(crate::app::SequenceSets::Evaluate),
+                    .in_set(crate::app::EvaluateSets::Structure),
             )
             .add_systems(Update, delay::manage_delay)
             .add_systems(
@@ -63,6 +90,16 @@ pub trait FragmentExt: Sized {
         self.limit(1)
     }
 
+    /// Evaluate false for `duration` after this fragment ends.
+    ///
+    /// Cooldowns are measured with the [`SequenceClock`] and
+    /// their remaining time is persisted with [`FragmentExt::save_as`].
+    ///
+    /// [`SequenceClock`]: crate::clock::SequenceClock
+    fn cooldown(self, duration: Duration) -> Cooldown<Self> {
+        Cooldown::new(self, duration)
+    }
+
     /// Always insert a true evaluation.
     ///
     /// This does not necessarily mean that the fragment will always run;
@@ -71,6 +108,38 @@ pub trait FragmentExt: Sized {
         AlwaysFragment::new(self)
     }
 
+    /// Skip this fragment when it evaluates to false inside a sequence,
+    /// rather than waiting for it to become true.
+    ///
+    /// Skipped fragments are counted as done for that pass through the sequence.
+    /// ```ignore
+    /// (
+    ///     "Morning!",
+    ///     "Bring an umbrella.".eval(is_raining).optional(),
+    ///     "See you later.",
+    /// )
+    /// ```
+    fn optional(self) -> Optional<Self> {
+        Optional::new(self)
+    }
+
+    /// Let this fragment begin `delay` after the item before it
+    /// in a sequence began, even if that item is still active.
+    ///
+    /// The sequence ends once every item has ended. Overlapping
+    /// [`optional`](FragmentExt::optional) items are still skipped
+    /// if they evaluate to false.
+    fn overlap(self, delay: Duration) -> Overlap<Self> {
+        Overlap::new(self, delay)
+    }
+
+    /// Name this fragment so [`goto`](label::goto) can jump to it.
+    ///
+    /// Labels are looked up within the fragment's tree.
+    fn label(self, label: impl Into<Cow<'static, str>>) -> Labeled<Self> {
+        Labeled::new(self, label.into())
+    }
+
     /// If this fragment evaluates to false,
     /// add a true evaluation to the passed in fragment B.
     fn or<B>(self, fragment: B) -> Or<Self, B> {
@@ -78,6 +147,12 @@ pub trait FragmentExt: Sized {
     }
 
     /// Add an evaluation to this fragment.
+    ///
+    /// The system can return any [`Evaluate`] type, so
+    /// Bevy's run conditions can be used directly.
+    /// ```ignore
+    /// "fragment".eval(resource_exists::<Shopkeep>.and(not(in_state(Night))))
+    /// ```
     fn eval<S, O, M>(self, system: S) -> Evaluated<Self, S, O, M>
     where
         S: IntoSystem<(), O, M> + 'static,
@@ -97,13 +172,35 @@ pub trait FragmentExt: Sized {
         EvaluatedWithId::new(self, system)
     }
 
+    /// Evaluate true on frames where an event of type `E` was received.
+    ///
+    /// Events are read once per frame for every fragment waiting on `E`.
+    /// ```ignore
+    /// "Welcome in!".on_event::<Interact>()
+    /// ```
+    fn on_event<E: Event>(self) -> OnEvent<Self, E> {
+        OnEvent::new(self)
+    }
+
+    /// Evaluate true on frames where an event of type `E`
+    /// that passes `filter` was received.
+    /// ```ignore
+    /// "Welcome in!".on_event_where(|e: &Interact| e.target == shopkeep)
+    /// ```
+    fn on_event_where<E: Event>(
+        self,
+        filter: impl Fn(&E) -> bool + Send + Sync + 'static,
+    ) -> OnEvent<Self, E> {
+        OnEvent::new_where(self, filter)
+    }
+
     /// Run a system when this fragment is first reached.
     ///
     /// The system can accept a shared or mutable reference
     /// to the fragment context with `InRef` in `InMut`.
     ///
     /// [OnStart] systems will be run from top-to-bottom.
-    /// ```
+    /// ```ignore
     /// (
     ///     "fragment".on_start(|| /* Second */),
     /// )
@@ -123,7 +220,7 @@ pub trait FragmentExt: Sized {
     /// to the fragment context with `InRef` in `InMut`.
     ///
     /// [OnEnd] systems will be run from bottom-to-top.
-    /// ```
+    /// ```ignore
     /// (
     ///     "fragment".on_end(|| /* First */),
     /// )
@@ -143,7 +240,7 @@ pub trait FragmentExt: Sized {
     /// to the fragment context with `InRef` in `InMut`.
     ///
     /// [OnVisit] systems will be run from top-to-bottom.
-    /// ```
+    /// ```ignore
     /// (
     ///     "fragment".on_visit(|| /* Second */),
     /// )
@@ -163,7 +260,7 @@ pub trait FragmentExt: Sized {
     /// to the fragment context with `InRef` in `InMut`.
     ///
     /// [OnInterrupt] systems will be run from bottom-to-top.
-    /// ```
+    /// ```ignore
     /// (
     ///     "fragment".on_interrupt(|| /* First */),
     /// )
@@ -0,0 +1,83 @@
+use crate::app::{add_systems_checked_in, EvaluateSets};
+use crate::prelude::*;
+use bevy_app::PreUpdate;
+use bevy_ecs::event::EventRegistry;
+use bevy_ecs::prelude::*;
+use std::marker::PhantomData;
+
+type EventFilter<E> = Box<dyn Fn(&E) -> bool + Send + Sync>;
+
+/// Evaluate true on frames where an event of type `E` was received.
+pub struct OnEvent<T, E> {
+    fragment: T,
+    filter: Option<EventFilter<E>>,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<T, E: Event> OnEvent<T, E> {
+    pub fn new(fragment: T) -> Self {
+        Self {
+            fragment,
+            filter: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only evaluate true for events that pass `filter`.
+    pub fn new_where(fragment: T, filter: impl Fn(&E) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            fragment,
+            filter: Some(Box::new(filter)),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct OnEventItem<E: Event>(Option<EventFilter<E>>);
+
+impl<T, E, C, D> IntoFragment<D, C> for OnEvent<T, E>
+where
+    T: IntoFragment<D, C>,
+    E: Event,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        commands
+            .entity(id.entity())
+            .insert(OnEventItem(self.filter));
+
+        commands.queue(|world: &mut World| {
+            if !world.contains_resource::<Events<E>>() {
+                EventRegistry::register_event::<E>(world);
+            }
+
+            add_systems_checked_in(
+                world,
+                PreUpdate,
+                EvaluateSets::Structure,
+                evaluate_events::<E>,
+            );
+        });
+
+        id
+    }
+}
+
+/// Read each event type once for every fragment waiting on it.
+pub(super) fn evaluate_events<E: Event>(
+    mut reader: EventReader<E>,
+    mut fragments: Query<(&mut Evaluation, &OnEventItem<E>)>,
+) {
+    let events: Vec<_> = reader.read().collect();
+
+    for (mut eval, item) in fragments.iter_mut() {
+        let received = match &item.0 {
+            Some(filter) => events.iter().any(|event| filter(event)),
+            None => !events.is_empty(),
+        };
+
+        eval.merge(received.evaluate());
+    }
+}
@@ -1,8 +1,18 @@
-use crate::prelude::*;
-use bevy_ecs::prelude::*;
+use super::{
+    call::Call,
+    cooldown::CooldownItem,
+    distribution::{tree_root, AvoidRecent, RecentMemory, RecentPicks},
+    limit::LimitItem,
+    select::SelectActiveNode,
+    sequence::SequenceCursor,
+    shuffle::ShuffleBag,
+    state_machine::StateMachine,
+};
+use crate::{clock::SequenceClock, prelude::*};
+use bevy_ecs::{prelude::*, query::QueryData};
 use bevy_log::prelude::*;
 use bevy_platform::collections::hash_map::{Entry, HashMap};
-use std::{any::TypeId, borrow::Cow, iter::zip};
+use std::{any::TypeId, borrow::Cow, iter::zip, time::Duration};
 
 /// Save a tree with a given name.
 pub struct Save<T> {
@@ -37,9 +47,81 @@ pub struct SavedSequence {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SavedNode {
     state: FragmentState,
+    /// The time left on this node's cooldown, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    cooldown: Option<Duration>,
+    /// The node's limit count, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    limit: Option<usize>,
+    /// The node's shuffle order and position, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    shuffle: Option<ShuffleBag>,
+    /// The node's recent random picks, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    recent: Option<RecentPicks>,
+    /// The node's kept select choice, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    select: Option<SelectActiveNode>,
+    /// The node's current state machine state, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    machine: Option<StateMachine>,
+    /// The node's skipped and rewound sequence items, if any.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    cursor: Option<SequenceCursor>,
     children: Vec<SavedNode>,
 }
 
+/// The node data that is loaded from a [SavedNode].
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub(super) struct SavedData {
+    state: &'static mut FragmentState,
+    cooldown: Option<&'static mut CooldownItem>,
+    limit: Option<&'static mut LimitItem>,
+    shuffle: Option<&'static mut ShuffleBag>,
+    recent: Option<&'static mut AvoidRecent>,
+    select: Option<&'static mut SelectActiveNode>,
+    machine: Option<&'static mut StateMachine>,
+    cursor: Option<&'static mut SequenceCursor>,
+    call: Has<Call>,
+}
+
+/// The node data that is written to a [SavedNode].
+#[derive(QueryData)]
+pub(super) struct NodeData {
+    state: &'static FragmentState,
+    children: Option<&'static Children>,
+    cooldown: Option<&'static CooldownItem>,
+    limit: Option<&'static LimitItem>,
+    shuffle: Option<&'static ShuffleBag>,
+    recent: Option<&'static AvoidRecent>,
+    select: Option<&'static SelectActiveNode>,
+    machine: Option<&'static StateMachine>,
+    cursor: Option<&'static SequenceCursor>,
+    call: Has<Call>,
+}
+
 #[derive(Debug, Component, Clone)]
 #[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -74,13 +156,36 @@ fn apply_saved_state(
     name: &str,
     node: Entity,
     state: &SavedNode,
-    nodes: &mut Query<&mut FragmentState, With<Fragment>>,
+    nodes: &mut Query<SavedData, With<Fragment>>,
     children_query: &Query<&Children>,
+    clock: &SequenceClock,
 ) -> Option<()> {
-    let mut frag_state = nodes.get_mut(node).ok()?;
-    let children = children_query.get(node).ok();
+    let mut data = nodes.get_mut(node).ok()?;
+    // called sequences are shared between trees, so they're saved separately
+    let children = children_query.get(node).ok().filter(|_| !data.call);
 
-    *frag_state = state.state.clone();
+    *data.state = state.state.clone();
+    if let Some(mut cooldown) = data.cooldown {
+        cooldown.set_remaining(state.cooldown.unwrap_or_default(), clock);
+    }
+    if let Some(mut limit) = data.limit {
+        limit.load(state.limit, &state.state);
+    }
+    if let (Some(mut shuffle), Some(saved)) = (data.shuffle, &state.shuffle) {
+        *shuffle = saved.clone();
+    }
+    if let (Some(mut recent), Some(saved)) = (data.recent, &state.recent) {
+        recent.load(saved);
+    }
+    if let (Some(mut select), Some(saved)) = (data.select, &state.select) {
+        *select = *saved;
+    }
+    if let (Some(mut machine), Some(saved)) = (data.machine, &state.machine) {
+        *machine = saved.clone();
+    }
+    if let Some(mut cursor) = data.cursor {
+        *cursor = state.cursor.clone().unwrap_or_default();
+    }
 
     match children {
         Some(children) if children.len() != state.children.len() => {
@@ -96,7 +201,7 @@ fn apply_saved_state(
         }
         Some(children) => {
             for (child, child_state) in zip(children, &state.children) {
-                apply_saved_state(name, *child, child_state, nodes, children_query);
+                apply_saved_state(name, *child, child_state, nodes, children_query, clock);
             }
         }
         _ => {}
@@ -108,9 +213,10 @@ fn apply_saved_state(
 pub(super) fn load_sequence(
     trigger: Trigger<OnAdd, SequenceState>,
     mut sequence: Query<&mut SequenceState>,
-    mut nodes: Query<&mut FragmentState, With<Fragment>>,
+    mut nodes: Query<SavedData, With<Fragment>>,
     children: Query<&Children>,
     saved: Res<SavedSequences>,
+    clock: Res<SequenceClock>,
 ) {
     let source = trigger.target();
 
@@ -123,24 +229,49 @@ pub(super) fn load_sequence(
     }
 
     if let Some(saved_nodes) = &sequence.nodes {
-        apply_saved_state(&sequence.name, source, saved_nodes, &mut nodes, &children);
+        apply_saved_state(
+            &sequence.name,
+            source,
+            saved_nodes,
+            &mut nodes,
+            &children,
+            &clock,
+        );
     }
 }
 
 fn get_saved_state(
     node: Entity,
     state: &mut SavedNode,
-    nodes: &Query<(&FragmentState, Option<&Children>), With<Fragment>>,
+    nodes: &Query<NodeData, With<Fragment>>,
+    memory: Option<&RecentMemory>,
+    clock: &SequenceClock,
 ) -> Option<()> {
-    let (node_state, children) = nodes.get(node).ok()?;
+    let data = nodes.get(node).ok()?;
 
-    state.state = node_state.clone();
+    state.state = data.state.clone();
+    state.cooldown = data
+        .cooldown
+        .map(|cooldown| cooldown.remaining(clock))
+        .filter(|remaining| !remaining.is_zero());
+    state.limit = data.limit.map(|limit| limit.count());
+    state.shuffle = data.shuffle.cloned();
+    state.recent = data
+        .recent
+        .and_then(|recent| recent.picks(node, memory))
+        .cloned();
+    state.select = data.select.filter(|select| select.is_chosen()).copied();
+    state.machine = data.machine.cloned();
+    state.cursor = data
+        .cursor
+        .filter(|cursor| **cursor != SequenceCursor::default())
+        .cloned();
 
-    if let Some(children) = children {
+    if let Some(children) = data.children.filter(|_| !data.call) {
         state.children.resize(children.len(), Default::default());
 
         for (child, child_state) in zip(children, &mut state.children) {
-            get_saved_state(*child, child_state, nodes);
+            get_saved_state(*child, child_state, nodes, memory, clock);
         }
     }
 
@@ -149,13 +280,18 @@ fn get_saved_state(
 
 pub(super) fn sync_sequence(
     mut sequences: Query<(Entity, &mut SequenceState)>,
-    nodes: Query<(&FragmentState, Option<&Children>), With<Fragment>>,
+    nodes: Query<NodeData, With<Fragment>>,
+    parents: Query<&ChildOf>,
+    memories: Query<&RecentMemory>,
     mut saved: ResMut<SavedSequences>,
+    clock: Res<SequenceClock>,
 ) {
     for (root, mut sequence) in sequences.iter_mut() {
         let state = sequence.nodes.get_or_insert(Default::default());
 
-        get_saved_state(root, state, &nodes);
+        // recent picks are kept by the root of the tree being saved
+        let tree = tree_root(root, |e| parents.get(e).ok().map(ChildOf::parent));
+        get_saved_state(root, state, &nodes, memories.get(tree).ok(), &clock);
 
         let entry = saved.0.entry(sequence.name.clone());
         match entry {
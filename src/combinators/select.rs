@@ -1,123 +1,519 @@
 use crate::fragment::children::IntoChildren;
+use crate::fragment::systems::{insert_fragment_system, FragmentSystemKind};
+use crate::fragment::Reachable;
 use crate::prelude::*;
-use bevy_ecs::component::{
-    ComponentId, ComponentsRegistrator, Mutable, RequiredComponents, StorageType,
-};
 use bevy_ecs::prelude::*;
-use bevy_ecs::system::{IntoSystem, SystemId};
+use bevy_ecs::system::{Adapt, IntoAdapterSystem, IntoSystem, SystemIn, SystemInput};
+use bevy_platform::collections::HashMap;
+use std::hash::Hash;
 use std::marker::PhantomData;
 
 /// A combinator that selects exactly one fragment from a tuple based on a system's output.
 ///
-/// The system should return a `usize` representing the chosen child's index.
+/// The system should return a `usize` representing the chosen child's index,
+/// or an `Option<usize>` where `None` selects no child.
 /// If the returned index is out of range, no child will be selected.
 pub struct SelectFragment<F, T, M> {
     fragments: F,
     system: T,
+    mode: SelectMode,
     _marker: PhantomData<fn() -> M>,
 }
 
-pub fn select<F, T, M>(fragments: F, system: T) -> SelectFragment<F, T, M>
+pub fn select<F, T, O, M>(fragments: F, system: T) -> SelectFragment<F, T, (O, M)>
 where
-    T: IntoSystem<(), usize, M> + 'static,
+    T: IntoSystem<(), O, M> + 'static,
+    O: SelectIndex,
 {
     SelectFragment {
         fragments,
         system,
+        mode: SelectMode::default(),
         _marker: PhantomData,
     }
 }
 
-/// A component inserted by the `Select` combinator to track the chosen index system.
-#[derive(Clone, Copy)]
-pub struct SelectSystem(SystemId<(), usize>);
+impl<F, T, M> SelectFragment<F, T, M> {
+    /// Set when the chooser is rerun.
+    pub fn mode(mut self, mode: SelectMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
 
-#[derive(Clone, Copy, Component)]
-pub(super) struct SelectActiveNode(usize);
+/// Determines when a select reruns its chooser.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub enum SelectMode {
+    /// Choose each time the select is triggered,
+    /// keeping the choice until it ends.
+    #[default]
+    EveryTrigger,
 
-// Here we automatically clean up the system when this component is removed.
-impl Component for SelectSystem {
-    const STORAGE_TYPE: StorageType = StorageType::Table;
+    /// Choose again before each visit, even while the select is active,
+    /// so each visit continues into the current choice.
+    ///
+    /// The choice is kept while any fragment within the select is playing.
+    EveryVisit,
 
-    type Mutability = Mutable;
+    /// Choose once and keep the choice.
+    ///
+    /// The choice is persisted with `save_as`.
+    OncePerSave,
+}
+
+/// The output of a select's chooser.
+pub trait SelectIndex: Send + Sync + 'static {
+    fn index(self) -> Option<usize>;
+}
 
-    fn register_component_hooks(hooks: &mut bevy_ecs::component::ComponentHooks) {
-        hooks.on_remove(|mut world, ctx| {
-            let eval = world.get::<SelectSystem>(ctx.entity).unwrap().0;
-            world.commands().unregister_system(eval);
-        });
+impl SelectIndex for usize {
+    fn index(self) -> Option<usize> {
+        Some(self)
     }
+}
 
-    fn register_required_components(
-        component_id: ComponentId,
-        components: &mut ComponentsRegistrator,
-        required_components: &mut RequiredComponents,
-        inheritance_depth: u16,
-        recursion_check_stack: &mut Vec<ComponentId>,
-    ) {
-        <Fragment as bevy_ecs::component::Component>::register_required_components(
-            component_id,
-            components,
-            required_components,
-            inheritance_depth,
-            recursion_check_stack,
-        );
+impl SelectIndex for Option<usize> {
+    fn index(self) -> Option<usize> {
+        self
+    }
+}
+
+/// A select's chosen child.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(Fragment, SelectMode)]
+pub(super) struct SelectActiveNode {
+    index: Option<usize>,
+    /// Whether a [SelectMode::OncePerSave] choice has been made.
+    chosen: bool,
+}
+
+impl SelectActiveNode {
+    /// Whether the choice is kept rather than rerun.
+    pub(super) fn is_chosen(&self) -> bool {
+        self.chosen
+    }
+}
+
+/// Whether a select's chooser should run this frame.
+fn should_choose(
+    state: &FragmentState,
+    reachable: &Reachable,
+    active: &SelectActiveNode,
+    mode: &SelectMode,
+) -> bool {
+    let rerun = match mode {
+        SelectMode::EveryTrigger => !state.active,
+        SelectMode::EveryVisit => state.active_events.is_empty(),
+        SelectMode::OncePerSave => !active.chosen,
+    };
+
+    rerun && reachable.0
+}
+
+type SelectGate = (
+    &'static FragmentState,
+    &'static Reachable,
+    &'static SelectActiveNode,
+    &'static SelectMode,
+);
+
+/// Stores a select's chosen index, rerunning the chooser
+/// according to its [SelectMode] while it's [Reachable].
+pub(super) struct SelectSystems;
+
+impl FragmentSystemKind<Option<usize>> for SelectSystems {
+    type Gate = SelectGate;
+
+    fn should_run(
+        (state, reachable, active, mode): (
+            &FragmentState,
+            &Reachable,
+            &SelectActiveNode,
+            &SelectMode,
+        ),
+    ) -> bool {
+        should_choose(state, reachable, active, mode)
+    }
+
+    fn apply(world: &mut World, fragment: FragmentId, index: Option<usize>) {
+        let Ok(mut entity) = world.get_entity_mut(fragment.entity()) else {
+            return;
+        };
+
+        let chosen = entity.get::<SelectMode>() == Some(&SelectMode::OncePerSave);
+        entity.insert(SelectActiveNode { index, chosen });
+        let Some(children) = entity.get::<Children>().map(|c| c.to_vec()) else {
+            return;
+        };
+
+        for (i, child) in children.into_iter().enumerate() {
+            if let Some(mut evaluation) = world.get_mut::<Evaluation>(child) {
+                evaluation.merge((index == Some(i)).evaluate());
+            }
+        }
     }
 }
 
-impl<C, Data, F, T, M> IntoFragment<Data, C> for SelectFragment<F, T, M>
+impl<C, Data, F, T, O, M> IntoFragment<Data, C> for SelectFragment<F, T, (O, M)>
 where
     Data: Threaded,
     F: IntoChildren<Data, C>,
-    T: IntoSystem<(), usize, M> + 'static,
+    T: IntoSystem<(), O, M> + Send + 'static,
+    O: SelectIndex,
 {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
         let children = self.fragments.into_children(context, commands);
 
-        // Register the provided system.
-        let system_id = commands.register_system(self.system);
-
-        // Spawn a parent entity with Select and SelectSystem
         let parent = commands
-            .spawn((SelectSystem(system_id), SelectActiveNode(0)))
+            .spawn((SelectActiveNode::default(), self.mode))
             .add_children(children.as_ref())
             .id();
+        let parent = FragmentId::new(parent);
+
+        insert_fragment_system::<SelectSystems, _, _, _, _>(
+            commands,
+            parent,
+            self.system.map(SelectIndex::index),
+        );
 
-        FragmentId::new(parent)
+        parent
     }
 }
 
+/// Keep evaluating the chosen child on frames where the chooser doesn't run.
+///
+/// Otherwise, the chooser's output is merged in [SelectSystems].
 pub(super) fn update_select_items(
     choices: Query<(
-        Entity,
         &Children,
         &FragmentState,
-        &SelectSystem,
+        &Reachable,
         &SelectActiveNode,
+        &SelectMode,
     )>,
-    mut commands: Commands,
+    mut evaluations: Query<&mut Evaluation>,
 ) {
-    let choices: Vec<_> = choices
-        .iter()
-        .map(|(e, c, state, s, active)| (e, c.to_vec(), !state.active, *s, *active))
-        .collect();
-
-    commands.queue(|world: &mut World| {
-        for (e, children, empty, choice_system, active) in choices {
-            let result = if empty {
-                let result = world.run_system(choice_system.0).unwrap();
-                world.entity_mut(e).insert(SelectActiveNode(result));
-                result
-            } else {
-                active.0
-            };
-
-            for (i, child) in children.iter().enumerate() {
-                let mut child = world.entity_mut(*child);
-                if let Some(mut evaluation) = child.get_mut::<Evaluation>() {
-                    evaluation.merge((result == i).evaluate());
-                }
+    for (children, state, reachable, active, mode) in choices.iter() {
+        if should_choose(state, reachable, active, mode) {
+            continue;
+        }
+
+        for (i, child) in children.iter().enumerate() {
+            if let Ok(mut evaluation) = evaluations.get_mut(child) {
+                evaluation.merge((active.index == Some(i)).evaluate());
             }
         }
-    });
+    }
+}
+
+/// A combinator that selects one of its branches by matching a system's output against each branch's key.
+pub struct SelectByFragment<S, B, G, M> {
+    system: S,
+    branches: B,
+    fallback: G,
+    mode: SelectMode,
+    _marker: PhantomData<fn() -> M>,
+}
+
+/// Select the branch whose key matches the output of `system`.
+///
+/// If no key matches, the [fallback](SelectByFragment::fallback)
+/// is selected, or nothing if there isn't one. If several branches
+/// share a key, the first is selected.
+///
+/// Like [select], the system is rerun according to its [SelectMode].
+/// It can read the fragment's ID and context through any [SelectInput].
+/// ```ignore
+/// select_by(
+///     |weather: Res<Weather>| *weather,
+///     (
+///         (Weather::Sunny, "What a lovely day."),
+///         (Weather::Rainy, ("It's pouring.", "Got an umbrella?")),
+///     ),
+/// )
+/// .fallback("Strange weather we're having.")
+/// ```
+pub fn select_by<S, B, M>(system: S, branches: B) -> SelectByFragment<S, B, NoFallback, M> {
+    SelectByFragment {
+        system,
+        branches,
+        fallback: NoFallback,
+        mode: SelectMode::default(),
+        _marker: PhantomData,
+    }
+}
+
+impl<S, B, M> SelectByFragment<S, B, NoFallback, M> {
+    /// Select `fragment` when no key matches.
+    pub fn fallback<F>(self, fragment: F) -> SelectByFragment<S, B, Fallback<F>, M> {
+        SelectByFragment {
+            system: self.system,
+            branches: self.branches,
+            fallback: Fallback(fragment),
+            mode: self.mode,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S, B, G, M> SelectByFragment<S, B, G, M> {
+    /// Set when the key system is rerun.
+    pub fn mode(mut self, mode: SelectMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// A [SelectByFragment] without a fallback.
+pub struct NoFallback;
+
+/// A [SelectByFragment]'s fallback branch.
+pub struct Fallback<F>(F);
+
+pub trait IntoFallback<Data: Threaded, C> {
+    fn into_fallback(self, context: &Context<C>, commands: &mut Commands) -> Option<FragmentId>;
+}
+
+impl<Data: Threaded, C> IntoFallback<Data, C> for NoFallback {
+    fn into_fallback(self, _: &Context<C>, _: &mut Commands) -> Option<FragmentId> {
+        None
+    }
+}
+
+impl<Data, C, F> IntoFallback<Data, C> for Fallback<F>
+where
+    Data: Threaded,
+    F: IntoFragment<Data, C>,
+{
+    fn into_fallback(self, context: &Context<C>, commands: &mut Commands) -> Option<FragmentId> {
+        Some(self.0.into_fragment(context, commands))
+    }
+}
+
+/// A collection of key and fragment pairs.
+pub trait IntoKeyedChildren<Data: Threaded, C, K> {
+    fn into_keyed_children(
+        self,
+        context: &Context<C>,
+        commands: &mut Commands,
+    ) -> (Vec<K>, Vec<Entity>);
+}
+
+macro_rules! keyed_children {
+    ($(($K:ident, $F:ident)),*) => {
+        #[allow(non_snake_case)]
+        impl<Data, C, Key, $($F),*> IntoKeyedChildren<Data, C, Key> for ($((Key, $F),)*)
+        where
+            Data: Threaded,
+            $($F: IntoFragment<Data, C>),*
+        {
+            fn into_keyed_children(
+                self,
+                context: &Context<C>,
+                commands: &mut Commands,
+            ) -> (Vec<Key>, Vec<Entity>) {
+                let ($(($K, $F),)*) = self;
+                let keys = vec![$($K),*];
+                let children = vec![$($F.into_fragment(context, commands).entity()),*];
+
+                (keys, children)
+            }
+        }
+    }
+}
+
+variadics_please::all_tuples!(keyed_children, 1, 15, K, F);
+
+impl<Data, C, K, F, const N: usize> IntoKeyedChildren<Data, C, K> for [(K, F); N]
+where
+    Data: Threaded,
+    F: IntoFragment<Data, C>,
+{
+    fn into_keyed_children(
+        self,
+        context: &Context<C>,
+        commands: &mut Commands,
+    ) -> (Vec<K>, Vec<Entity>) {
+        self.into_iter()
+            .map(|(key, fragment)| (key, fragment.into_fragment(context, commands).entity()))
+            .unzip()
+    }
+}
+
+impl<Data, C, K, F> IntoKeyedChildren<Data, C, K> for Vec<(K, F)>
+where
+    Data: Threaded,
+    F: IntoFragment<Data, C>,
+{
+    fn into_keyed_children(
+        self,
+        context: &Context<C>,
+        commands: &mut Commands,
+    ) -> (Vec<K>, Vec<Entity>) {
+        self.into_iter()
+            .map(|(key, fragment)| (key, fragment.into_fragment(context, commands).entity()))
+            .unzip()
+    }
+}
+
+/// Inputs that can be provided to a [select_by] system.
+///
+/// A system can take no input, the fragment's ID with `In<FragmentId>`,
+/// the fragment's context with `InRef<C>`, or both as a tuple.
+/// ```ignore
+/// fn mood((In(fragment), InRef(npc)): (In<FragmentId>, InRef<Npc>), moods: Query<&Mood>) -> Mood {
+///     // ...
+/// }
+/// ```
+pub trait SelectInput<C>: SystemInput {
+    fn with_input<R>(
+        context: &Context<C>,
+        fragment: FragmentId,
+        func: impl FnOnce(Self::Inner<'_>) -> R,
+    ) -> R;
+}
+
+impl<C> SelectInput<C> for () {
+    fn with_input<R>(_: &Context<C>, _: FragmentId, func: impl FnOnce(()) -> R) -> R {
+        func(())
+    }
+}
+
+impl<C> SelectInput<C> for In<FragmentId> {
+    fn with_input<R>(
+        _: &Context<C>,
+        fragment: FragmentId,
+        func: impl FnOnce(FragmentId) -> R,
+    ) -> R {
+        func(fragment)
+    }
+}
+
+impl<C: 'static> SelectInput<C> for InRef<'_, C> {
+    fn with_input<R>(context: &Context<C>, _: FragmentId, func: impl FnOnce(&C) -> R) -> R {
+        func(&context.read().unwrap())
+    }
+}
+
+impl<C: 'static> SelectInput<C> for (In<FragmentId>, InRef<'_, C>) {
+    fn with_input<R>(
+        context: &Context<C>,
+        fragment: FragmentId,
+        func: impl FnOnce((FragmentId, &C)) -> R,
+    ) -> R {
+        func((fragment, &context.read().unwrap()))
+    }
+}
+
+/// Adapts a [select_by] system so the fragment systems
+/// driver can run it with only the fragment's ID.
+struct WithContext<C, I> {
+    context: Context<C>,
+    _marker: PhantomData<fn() -> I>,
+}
+
+impl<C, I, S> Adapt<S> for WithContext<C, I>
+where
+    C: Send + Sync + 'static,
+    I: SelectInput<C> + 'static,
+    S: System<In = I>,
+{
+    type In = In<FragmentId>;
+    type Out = S::Out;
+
+    fn adapt(
+        &mut self,
+        fragment: FragmentId,
+        run_system: impl FnOnce(SystemIn<'_, S>) -> S::Out,
+    ) -> S::Out {
+        I::with_input(&self.context, fragment, run_system)
+    }
+}
+
+/// The keys of a [select_by] fragment's children, in order.
+#[derive(Component)]
+pub(super) struct SelectKeys<K> {
+    keys: HashMap<K, usize>,
+    fallback: Option<usize>,
+}
+
+/// Stores the index of the child matching a [select_by] system's key.
+pub(super) struct KeySelectSystems;
+
+impl<K: Hash + Eq + Threaded> FragmentSystemKind<K> for KeySelectSystems {
+    type Gate = SelectGate;
+
+    fn should_run(
+        (state, reachable, active, mode): (
+            &FragmentState,
+            &Reachable,
+            &SelectActiveNode,
+            &SelectMode,
+        ),
+    ) -> bool {
+        should_choose(state, reachable, active, mode)
+    }
+
+    fn apply(world: &mut World, fragment: FragmentId, key: K) {
+        let Some(keys) = world.get::<SelectKeys<K>>(fragment.entity()) else {
+            return;
+        };
+
+        let index = keys.keys.get(&key).copied().or(keys.fallback);
+        SelectSystems::apply(world, fragment, index);
+    }
+}
+
+impl<Data, C, S, B, G, K, I, M> IntoFragment<Data, C> for SelectByFragment<S, B, G, (K, I, M)>
+where
+    Data: Threaded,
+    C: Send + Sync + 'static,
+    S: IntoSystem<I, K, M> + Send + 'static,
+    B: IntoKeyedChildren<Data, C, K>,
+    G: IntoFallback<Data, C>,
+    K: Hash + Eq + Threaded,
+    I: SelectInput<C> + 'static,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let (keys, mut children) = self.branches.into_keyed_children(context, commands);
+        let fallback = self
+            .fallback
+            .into_fallback(context, commands)
+            .map(|fallback| {
+                children.push(fallback.entity());
+                children.len() - 1
+            });
+
+        let mut indices = HashMap::default();
+        for (i, key) in keys.into_iter().enumerate() {
+            indices.entry(key).or_insert(i);
+        }
+
+        let parent = commands
+            .spawn((
+                SelectActiveNode::default(),
+                self.mode,
+                SelectKeys {
+                    keys: indices,
+                    fallback,
+                },
+            ))
+            .add_children(&children)
+            .id();
+        let parent = FragmentId::new(parent);
+
+        let adapter = WithContext {
+            context: context.clone(),
+            _marker: PhantomData::<fn() -> I>,
+        };
+        insert_fragment_system::<KeySelectSystems, _, _, _, _>(
+            commands,
+            parent,
+            IntoAdapterSystem::new(adapter, IntoSystem::into_system(self.system)),
+        );
+
+        parent
+    }
 }
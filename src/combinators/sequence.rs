@@ -1,44 +1,413 @@
+use crate::clock::SequenceClock;
 use crate::fragment::event::{BeginStage, EndStage, MapContext, MapFn, StageEvent};
+use crate::fragment::{is_active_concurrent, Concurrent};
 use crate::prelude::*;
 use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use std::time::Duration;
 
 #[derive(Component)]
-#[require(Fragment)]
+#[require(Fragment, SequenceCursor, Concurrent)]
 pub struct Sequence;
 
+/// A sequence's position within its current pass.
+///
+/// An item is normally done once it has completed more times than its
+/// sequence. The cursor adjusts that without touching the items'
+/// [FragmentState]s: skipped items count as done until the sequence
+/// completes, and items that didn't play in a completed pass count as
+/// having completed in it, while
+/// rewound items have to complete again before they do.
+/// Items are tracked by their index in the sequence.
+///
+/// Timelines keep a cursor for their tracks too.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequenceCursor {
+    /// The sequence's completion count the cursor has caught up to.
+    pass: usize,
+    /// The items skipped in `pass`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    skipped: Vec<usize>,
+    /// For each item, the number of its completions that were rewound.
+    #[cfg_attr(feature = "serde", serde(default))]
+    rewound: Vec<usize>,
+    /// For each item, the number of earlier passes it was skipped in.
+    #[cfg_attr(feature = "serde", serde(default))]
+    lagged: Vec<usize>,
+}
+
+impl SequenceCursor {
+    /// Whether the item at `index` is done in the sequence's current pass.
+    pub fn is_done(&self, index: usize, item: &FragmentState, outer_completed: usize) -> bool {
+        let skipped = self.pass == outer_completed && self.skipped.contains(&index);
+
+        skipped || self.completions(index, item) > outer_completed
+    }
+
+    /// The item's completions, counting each pass it was
+    /// skipped in and leaving out the ones that were rewound.
+    fn completions(&self, index: usize, item: &FragmentState) -> usize {
+        let lagged = self.lagged.get(index).copied().unwrap_or_default();
+        let rewound = self.rewound.get(index).copied().unwrap_or_default();
+
+        (item.completed + lagged).saturating_sub(rewound)
+    }
+
+    /// Move to the pass `outer_completed`. Items that didn't play in a
+    /// completed pass count as having been skipped in it.
+    ///
+    /// This has to run before the cursor is updated in a new pass.
+    pub(crate) fn catch_up<'a>(
+        &mut self,
+        items: impl IntoIterator<Item = Option<&'a FragmentState>>,
+        outer_completed: usize,
+    ) {
+        if self.pass == outer_completed {
+            return;
+        }
+
+        if self.pass < outer_completed {
+            for (index, item) in items.into_iter().enumerate() {
+                let Some(item) = item else {
+                    continue;
+                };
+
+                let behind = outer_completed.saturating_sub(self.completions(index, item));
+                if behind > 0 {
+                    if self.lagged.len() <= index {
+                        self.lagged.resize(index + 1, 0);
+                    }
+                    self.lagged[index] += behind;
+                }
+            }
+        }
+
+        self.pass = outer_completed;
+        self.skipped.clear();
+    }
+
+    /// Count the item at `index` as done for the rest of the current pass.
+    pub(crate) fn skip(&mut self, index: usize) {
+        if !self.skipped.contains(&index) {
+            self.skipped.push(index);
+        }
+    }
+
+    /// Count the item at `index` as not played in the current pass.
+    pub(crate) fn rewind(&mut self, index: usize, item: &FragmentState, outer_completed: usize) {
+        self.skipped.retain(|skipped| *skipped != index);
+
+        if self.rewound.len() <= index {
+            self.rewound.resize(index + 1, 0);
+        }
+
+        let lagged = self.lagged.get(index).copied().unwrap_or_default();
+        let rewound = &mut self.rewound[index];
+        *rewound = (*rewound).max((item.completed + lagged).saturating_sub(outer_completed));
+    }
+}
+
+/// Skip a fragment in a sequence when it evaluates to false.
+pub struct Optional<T> {
+    fragment: T,
+}
+
+impl<T> Optional<T> {
+    pub fn new(fragment: T) -> Self {
+        Self { fragment }
+    }
+}
+
+/// Marks a sequence item that is skipped when it evaluates to false.
+///
+/// Skipped items are recorded in the sequence's [SequenceCursor].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct OptionalItem {
+    /// Whether the sequence would let this item begin now.
+    ///
+    /// Optional items aren't evaluated by their sequence, since
+    /// they're skipped according to their own evaluations.
+    can_begin: bool,
+}
+
+impl OptionalItem {
+    pub fn can_begin(&self) -> bool {
+        self.can_begin
+    }
+}
+
+impl<T, C, D> IntoFragment<D, C> for Optional<T>
+where
+    T: IntoFragment<D, C>,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        commands.entity(id.entity()).insert(OptionalItem::default());
+
+        id
+    }
+}
+
+/// Let a sequence item begin before the item before it ends.
+pub struct Overlap<T> {
+    fragment: T,
+    delay: Duration,
+}
+
+impl<T> Overlap<T> {
+    pub fn new(fragment: T, delay: Duration) -> Self {
+        Self { fragment, delay }
+    }
+}
+
+/// Marks a sequence item that can begin this long after the
+/// item before it began, even if that item is still active.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct OverlapItem(pub Duration);
+
+impl<T, C, D> IntoFragment<D, C> for Overlap<T>
+where
+    T: IntoFragment<D, C>,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        commands.entity(id.entity()).insert(OverlapItem(self.delay));
+
+        id
+    }
+}
+
+/// A sequence whose items begin `delay` after the previous item began.
+pub struct SequenceOverlap<F> {
+    fragments: F,
+    delay: Duration,
+}
+
+/// A sequence whose items each begin `delay` after the previous item began,
+/// even if the previous item is still active.
+///
+/// Items with their own [`FragmentExt::overlap`] keep their delay.
+/// The sequence ends once every item has ended. `fragments` must be
+/// a sequence, such as a tuple of fragments, or this has no effect.
+/// ```ignore
+/// sequence_overlap(
+///     ("Did you hear that?", "Hear what?", "Shh!"),
+///     Duration::from_millis(800),
+/// )
+/// ```
+pub fn sequence_overlap<F>(fragments: F, delay: Duration) -> SequenceOverlap<F> {
+    SequenceOverlap { fragments, delay }
+}
+
+impl<F, C, D> IntoFragment<D, C> for SequenceOverlap<F>
+where
+    F: IntoFragment<D, C>,
+    D: Threaded,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragments.into_fragment(context, commands);
+
+        let delay = self.delay;
+        commands.queue(move |world: &mut World| {
+            let Ok(sequence) = world.get_entity(id.entity()) else {
+                return;
+            };
+
+            if !sequence.contains::<Sequence>() {
+                warn!("`sequence_overlap` only applies to sequences");
+                return;
+            }
+
+            let Some(children) = sequence.get::<Children>().map(|c| c.to_vec()) else {
+                return;
+            };
+
+            for child in children.into_iter().skip(1) {
+                world
+                    .entity_mut(child)
+                    .entry::<OverlapItem>()
+                    .or_insert(OverlapItem(delay));
+            }
+        });
+
+        id
+    }
+}
+
+/// Decide which of each sequence's items can begin.
+///
+/// This is the only place a sequence's order is decided. The next item
+/// is evaluated to true and the rest to false. Optional items are left
+/// to their own evaluations, and are instead marked with whether they
+/// can begin. The tree walk then plays the first item that can.
 pub(super) fn update_sequence_items(
-    q: Query<(&Children, &FragmentState), With<Sequence>>,
-    mut children: Query<(&mut Evaluation, &FragmentState)>,
+    mut q: Query<(Entity, &Children, &mut SequenceCursor), With<Sequence>>,
+    mut children: Query<(
+        &mut Evaluation,
+        &FragmentState,
+        Option<&mut OptionalItem>,
+        Option<&OverlapItem>,
+        Has<Concurrent>,
+    )>,
+    clock: Res<SequenceClock>,
 ) {
-    for (seq, outer_state) in q.iter() {
+    for (seq_entity, seq, mut cursor) in q.iter_mut() {
+        let Ok((_, outer_state, ..)) = children.get(seq_entity) else {
+            continue;
+        };
         let inactive = outer_state.active_events.is_empty();
+        let outer_completed = outer_state.completed;
+
+        cursor.catch_up(
+            seq.iter()
+                .map(|child| children.get(child).ok().map(|(_, state, ..)| state)),
+            outer_completed,
+        );
+
+        // the furthest item that has played or is playing in this pass
+        let furthest = seq.iter().enumerate().rposition(|(i, child)| {
+            children.get(child).is_ok_and(|(_, state, ..)| {
+                cursor.is_done(i, state, outer_completed) || !state.active_events.is_empty()
+            })
+        });
 
         // look for the first item that has finished equal to the container
         let mut first_selected = false;
-        for child in seq.iter() {
-            let Ok((mut eval, state)) = children.get_mut(child) else {
+        // when the previous item began, if it played in this pass
+        let mut previous_began = None;
+        for (i, child) in seq.iter().enumerate() {
+            let Ok((mut eval, state, mut optional, overlap, concurrent)) = children.get_mut(child)
+            else {
+                previous_began = None;
                 continue;
             };
 
-            if inactive
-                && !first_selected
-                && state.active_events.is_empty()
-                && state.completed <= outer_state.completed
-            {
+            let overlapping = overlap.is_some_and(|overlap| {
+                previous_began.is_some_and(|began: Duration| {
+                    clock.elapsed().saturating_sub(began) >= overlap.0
+                })
+            });
+            let active = !state.active_events.is_empty();
+
+            let done = cursor.is_done(i, state, outer_completed);
+            let can_begin = (inactive || overlapping) && !first_selected && !active && !done;
+
+            // optional items are left to their own evaluations,
+            // and are skipped once a later item plays
+            if let Some(optional) = optional.as_mut() {
+                if !done && !active && furthest.is_some_and(|furthest| i < furthest) {
+                    cursor.skip(i);
+                    optional.set_if_neq(OptionalItem { can_begin: false });
+                    continue;
+                }
+
+                optional.set_if_neq(OptionalItem { can_begin });
+            }
+
+            previous_began = state.last_begin.filter(|_| active || done);
+
+            if optional.is_some() {
+                continue;
+            }
+
+            if can_begin {
                 first_selected = true;
                 eval.merge(true.evaluate());
 
                 continue;
             }
 
+            if is_active_concurrent(state, concurrent) {
+                continue;
+            }
+
             eval.merge(false.evaluate());
         }
     }
 }
 
-fn map_begin(input: MapContext<BeginStage>, first: Option<Entity>) -> StageEvent<BeginStage> {
+/// Whether a sequence is done once `child` ends.
+///
+/// No other item can still be active, and every item after `child`
+/// must have ended in this pass or be an optional item that is skipped.
+fn is_done(world: &World, (items, cursor, outer_completed): SequenceItems, child: Entity) -> bool {
+    let Some(position) = items.iter().position(|item| *item == child) else {
+        return false;
+    };
+
+    let others_ended = items.iter().filter(|item| **item != child).all(|item| {
+        world
+            .get::<FragmentState>(*item)
+            .is_none_or(|state| state.active_events.is_empty())
+    });
+
+    let rest_done = items
+        .iter()
+        .enumerate()
+        .skip(position + 1)
+        .all(|(i, item)| {
+            let Ok(item) = world.get_entity(*item) else {
+                return true;
+            };
+            let Some(state) = item.get::<FragmentState>() else {
+                return true;
+            };
+
+            let skipped = item.contains::<OptionalItem>()
+                && item
+                    .get::<Evaluation>()
+                    .is_none_or(|eval| eval.result == Some(false));
+
+            cursor.is_done(i, state, outer_completed) || skipped
+        });
+
+    others_ended && rest_done
+}
+
+/// Whether every item before `position` is optional and hasn't played in this pass.
+fn all_unplayed(
+    world: &World,
+    (items, cursor, outer_completed): SequenceItems,
+    position: usize,
+) -> bool {
+    items[..position].iter().enumerate().all(|(i, item)| {
+        world.get_entity(*item).is_ok_and(|item| {
+            item.contains::<OptionalItem>()
+                && item
+                    .get::<FragmentState>()
+                    .is_some_and(|state| !cursor.is_done(i, state, outer_completed))
+        })
+    })
+}
+
+/// A sequence's items, cursor and completion count.
+type SequenceItems<'a> = (&'a [Entity], &'a SequenceCursor, usize);
+
+fn sequence_items(world: &World, sequence: Entity) -> Option<SequenceItems<'_>> {
+    let sequence = world.get_entity(sequence).ok()?;
+    let children = sequence.get::<Children>()?;
+    let cursor = sequence.get::<SequenceCursor>()?;
+    let completed = sequence.get::<FragmentState>()?.completed;
+
+    Some((children, cursor, completed))
+}
+
+fn map_begin(
+    world: &World,
+    input: MapContext<BeginStage>,
+    first: Option<Entity>,
+) -> StageEvent<BeginStage> {
+    // an item is first if every item before it was skipped
     let first = match (first, input.child) {
-        (Some(first), Some(child)) => first == child,
+        (Some(first), Some(child)) if first == child => true,
+        (_, Some(child)) => sequence_items(world, input.target).is_some_and(|sequence| {
+            let position = sequence.0.iter().position(|item| *item == child);
+            position.is_some_and(|position| all_unplayed(world, sequence, position))
+        }),
         _ => false,
     };
 
@@ -55,11 +424,11 @@ fn map_begin(input: MapContext<BeginStage>, first: Option<Entity>) -> StageEvent
     }
 }
 
-fn map_end(input: MapContext<EndStage>, last: Option<Entity>) -> StageEvent<EndStage> {
-    let last = match (last, input.child) {
-        (Some(last), Some(child)) => last == child,
-        _ => false,
-    };
+fn map_end(world: &World, input: MapContext<EndStage>) -> StageEvent<EndStage> {
+    // an item is last if every other item is done or will be skipped
+    let last = input.child.is_some_and(|child| {
+        sequence_items(world, input.target).is_some_and(|sequence| is_done(world, sequence, child))
+    });
 
     if last && input.event.stage == EndStage::End {
         StageEvent {
@@ -91,10 +460,9 @@ macro_rules! seq_frag {
                 ];
 
                 let first = children.first().copied();
-                let last = children.last().copied();
 
-                let map_begin = MapFn::function(move |input| map_begin(input, first));
-                let map_end = MapFn::function(move |input| map_end(input, last));
+                let map_begin = MapFn::world_function(move |world, input| map_begin(world, input, first));
+                let map_end = MapFn::world_function(map_end);
                 FragmentId::new(commands.spawn((Sequence, map_begin, map_end)).add_children(&children).id())
             }
         }
@@ -102,3 +470,50 @@ macro_rules! seq_frag {
 }
 
 variadics_please::all_tuples_with_size!(seq_frag, 0, 23, T);
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::test_app::{app, run, spawn};
+    use bevy_ecs::prelude::*;
+
+    #[test]
+    fn optional_items_play_when_true() {
+        let mut app = app();
+        spawn(&mut app, ("a", "b".optional(), "c").always().once());
+
+        assert_eq!(run(&mut app, 10), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn optional_items_are_skipped_when_false() {
+        let mut app = app();
+        spawn(
+            &mut app,
+            ("a", "b".eval(|| false).optional(), "c").always().once(),
+        );
+
+        assert_eq!(run(&mut app, 10), ["a", "c"]);
+    }
+
+    #[test]
+    fn skipped_items_play_in_the_next_pass() {
+        #[derive(Resource, Default)]
+        struct Pass(usize);
+
+        let mut app = app();
+        app.init_resource::<Pass>();
+        spawn(
+            &mut app,
+            (
+                "a",
+                "b".eval(|pass: Res<Pass>| pass.0 > 0).optional(),
+                "c".on_end(|mut pass: ResMut<Pass>| pass.0 += 1),
+            )
+                .always()
+                .limit(2),
+        );
+
+        assert_eq!(run(&mut app, 20), ["a", "c", "a", "b", "c"]);
+    }
+}
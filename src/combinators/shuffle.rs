@@ -0,0 +1,104 @@
+use crate::{
+    fragment::{
+        children::IntoChildren,
+        event::{EndStage, InsertEndDown},
+    },
+    prelude::*,
+};
+use bevy_ecs::prelude::*;
+use rand::seq::SliceRandom;
+
+/// A fragment that plays its children in a random order,
+/// reshuffling only once every child has played.
+pub struct ShuffleFragment<F> {
+    fragments: F,
+}
+
+/// A fragment that plays its children in a random order,
+/// reshuffling only once every child has played.
+///
+/// Unlike [choice], the same child won't play twice in a row.
+///
+/// [choice]: super::distribution::choice
+pub fn shuffle<F>(fragments: F) -> ShuffleFragment<F> {
+    ShuffleFragment { fragments }
+}
+
+/// The order and position of a shuffled fragment's children.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+#[require(Fragment)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShuffleBag {
+    order: Vec<usize>,
+    position: usize,
+}
+
+impl ShuffleBag {
+    /// The index of the child that will be played next.
+    pub fn current(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+
+    fn shuffle(&mut self, len: usize) {
+        let last = self.order.last().copied();
+
+        self.order.clear();
+        self.order.extend(0..len);
+        self.order.shuffle(&mut rand::rng());
+        self.position = 0;
+
+        // avoid repeating the last child across reshuffles
+        if len > 1 && self.order.first().copied() == last {
+            self.order.swap(0, len - 1);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+}
+
+impl<D, C, F> IntoFragment<D, C> for ShuffleFragment<F>
+where
+    D: Threaded,
+    F: IntoChildren<D, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let children = self.fragments.into_children(context, commands);
+
+        let mut entity = commands.spawn(ShuffleBag::default());
+        let id = entity.id();
+
+        entity
+            .add_children(children.as_ref())
+            .insert_end_down(move |stage, world| {
+                if matches!(stage.stage, EndStage::End | EndStage::Interrupt)
+                    && let Some(mut bag) = world.get_mut::<ShuffleBag>(id)
+                {
+                    bag.advance();
+                }
+            });
+
+        FragmentId::new(id)
+    }
+}
+
+pub(super) fn update_shuffle_items(
+    mut bags: Query<(&Children, &mut ShuffleBag)>,
+    mut children_query: Query<&mut Evaluation>,
+) {
+    for (children, mut bag) in bags.iter_mut() {
+        let exhausted = bag.position >= bag.order.len();
+        if exhausted || bag.order.len() != children.len() {
+            bag.shuffle(children.len());
+        }
+
+        let current = bag.current();
+        for (i, child) in children.iter().enumerate() {
+            if let Ok(mut evaluation) = children_query.get_mut(child) {
+                evaluation.merge((current == Some(i)).evaluate());
+            }
+        }
+    }
+}
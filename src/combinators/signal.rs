@@ -0,0 +1,304 @@
+use crate::fragment::event::{IdPair, InsertBeginDown, InsertEndUp};
+use crate::fragment::Leaf;
+use crate::prelude::*;
+use bevy_ecs::{component::HookContext, entity::EntityHashSet, prelude::*, world::DeferredWorld};
+use bevy_log::prelude::*;
+use bevy_platform::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+
+/// Named signals and barriers shared by every tree in a world.
+///
+/// Signals raised by [emit_signal] and arrivals at a [barrier] are
+/// tracked by the fragment that caused them, and are cleared when
+/// that fragment is despawned.
+#[derive(Debug, Default, Resource)]
+pub struct Signals {
+    raised: HashMap<Cow<'static, str>, EntityHashSet>,
+    arrived: HashMap<Cow<'static, str>, EntityHashSet>,
+}
+
+impl Signals {
+    /// Whether a signal has been raised.
+    pub fn is_raised(&self, name: &str) -> bool {
+        self.raised
+            .get(name)
+            .is_some_and(|sources| !sources.is_empty())
+    }
+
+    /// Raise a signal outside of any tree.
+    ///
+    /// Signals raised this way are kept until they're cleared.
+    pub fn raise(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.raise_from(name.into(), Entity::PLACEHOLDER);
+    }
+
+    /// Lower a signal, no matter what raised it.
+    pub fn clear(&mut self, name: &str) {
+        self.raised.remove(name);
+    }
+
+    /// The number of sequences waiting at a barrier.
+    pub fn arrived(&self, name: &str) -> usize {
+        self.arrived.get(name).map_or(0, |arrived| arrived.len())
+    }
+
+    fn raise_from(&mut self, name: Cow<'static, str>, source: Entity) {
+        self.raised.entry(name).or_default().insert(source);
+    }
+
+    /// Forget everything caused by a fragment.
+    fn remove(&mut self, name: &str, fragment: Entity) {
+        if let Some(sources) = self.raised.get_mut(name) {
+            sources.remove(&fragment);
+        }
+        if let Some(arrived) = self.arrived.get_mut(name) {
+            arrived.remove(&fragment);
+        }
+    }
+}
+
+fn clear_fragment<T: Component + AsRef<str>>(mut world: DeferredWorld, context: HookContext) {
+    let Some(name) = world
+        .get::<T>(context.entity)
+        .map(|item| item.as_ref().to_owned())
+    else {
+        return;
+    };
+
+    if let Some(mut signals) = world.get_resource_mut::<Signals>() {
+        signals.remove(&name, context.entity);
+    }
+}
+
+/// A fragment that raises a signal and ends immediately.
+pub struct EmitSignalFragment {
+    name: Cow<'static, str>,
+}
+
+/// A fragment that raises a signal and ends immediately.
+///
+/// The signal stays raised for any [wait_signal] in any tree until it's
+/// cleared with [`Signals::clear`] or this fragment is despawned.
+/// ```ignore
+/// // Alice's tree
+/// ("Did you see that?", emit_signal("alice_done"))
+///
+/// // Bob's tree
+/// (wait_signal("alice_done"), "I sure did.")
+/// ```
+pub fn emit_signal(name: impl Into<Cow<'static, str>>) -> EmitSignalFragment {
+    EmitSignalFragment { name: name.into() }
+}
+
+#[derive(Debug, Component)]
+#[require(Leaf)]
+#[component(on_remove = clear_fragment::<EmitSignal>)]
+pub struct EmitSignal(pub Cow<'static, str>);
+
+impl AsRef<str> for EmitSignal {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<Data, C> IntoFragment<Data, C> for EmitSignalFragment
+where
+    Data: Threaded,
+{
+    fn into_fragment(self, _: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let mut emit = commands.spawn(EmitSignal(self.name.clone()));
+
+        let entity = emit.id();
+        emit.insert_begin_down(move |event, world| {
+            world
+                .get_resource_or_init::<Signals>()
+                .raise_from(self.name.clone(), entity);
+            world.send_event(FragmentEndEvent::new(event.id, false));
+        });
+
+        FragmentId::new(entity)
+    }
+}
+
+/// A fragment that can only be played once a signal is raised, ending immediately.
+pub struct WaitSignalFragment {
+    name: Cow<'static, str>,
+}
+
+/// A fragment that can only be played once a signal is raised, ending immediately.
+///
+/// Place it before the fragments that should wait on the signal.
+/// See [emit_signal].
+pub fn wait_signal(name: impl Into<Cow<'static, str>>) -> WaitSignalFragment {
+    WaitSignalFragment { name: name.into() }
+}
+
+#[derive(Debug, Component)]
+#[require(Leaf)]
+pub struct WaitSignal(pub Cow<'static, str>);
+
+impl<Data, C> IntoFragment<Data, C> for WaitSignalFragment
+where
+    Data: Threaded,
+{
+    fn into_fragment(self, _: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = commands
+            .spawn(WaitSignal(self.name))
+            .insert_begin_down(|event, world| {
+                world.send_event(FragmentEndEvent::new(event.id, false));
+            })
+            .id();
+
+        FragmentId::new(id)
+    }
+}
+
+/// A fragment that waits until a number of sequences have reached it.
+pub struct BarrierFragment {
+    name: Cow<'static, str>,
+    count: usize,
+}
+
+/// A fragment that waits until `count` sequences have reached it.
+///
+/// Barriers with the same name in any tree are counted together, and
+/// should all be given the same `count`. Once enough sequences are waiting,
+/// every waiting barrier ends at once, and the barrier can be reached again.
+/// A barrier that is interrupted while waiting no longer counts as arrived.
+/// ```ignore
+/// // Alice's tree
+/// ("I'll meet you at the gate.", barrier("gate", 2), "There you are!")
+///
+/// // Bob's tree
+/// ("On my way.", barrier("gate", 2), "Sorry I'm late.")
+/// ```
+pub fn barrier(name: impl Into<Cow<'static, str>>, count: usize) -> BarrierFragment {
+    let name = name.into();
+    if count == 0 {
+        warn!("barrier \"{name}\" has a count of 0, so it never waits");
+    }
+
+    BarrierFragment { name, count }
+}
+
+#[derive(Debug, Component)]
+#[require(Leaf)]
+#[component(on_remove = clear_fragment::<Barrier>)]
+pub struct Barrier {
+    name: Cow<'static, str>,
+    count: usize,
+    /// The event to end once the barrier is released.
+    waiting: Option<IdPair>,
+}
+
+impl AsRef<str> for Barrier {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<Data, C> IntoFragment<Data, C> for BarrierFragment
+where
+    Data: Threaded,
+{
+    fn into_fragment(self, _: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let mut barrier = commands.spawn(Barrier {
+            name: self.name,
+            count: self.count,
+            waiting: None,
+        });
+
+        let entity = barrier.id();
+        barrier
+            .insert_begin_down(move |event, world| {
+                let Some(mut barrier) = world.get_mut::<Barrier>(entity) else {
+                    return;
+                };
+
+                barrier.waiting = Some(event.id);
+                let name = barrier.name.clone();
+                world
+                    .get_resource_or_init::<Signals>()
+                    .arrived
+                    .entry(name)
+                    .or_default()
+                    .insert(entity);
+            })
+            .insert_end_up(move |_, world| {
+                // a barrier that ends without being released, like
+                // when it's interrupted, is withdrawn
+                withdraw(world, entity);
+            });
+
+        FragmentId::new(entity)
+    }
+}
+
+/// Stop counting a barrier as arrived.
+fn withdraw(world: &mut World, entity: Entity) {
+    let Some(mut barrier) = world.get_mut::<Barrier>(entity) else {
+        return;
+    };
+
+    if barrier.waiting.take().is_none() {
+        return;
+    }
+
+    let name = barrier.name.clone();
+    if let Some(mut signals) = world.get_resource_mut::<Signals>() {
+        signals.remove(&name, entity);
+    }
+}
+
+pub(super) fn evaluate_signals(
+    mut waits: Query<(&mut Evaluation, &WaitSignal)>,
+    signals: Res<Signals>,
+) {
+    for (mut eval, wait) in waits.iter_mut() {
+        eval.merge(signals.is_raised(&wait.0).evaluate());
+    }
+}
+
+/// Release every barrier with enough sequences waiting at it.
+pub(super) fn update_barriers(
+    mut barriers: Query<(&mut Barrier, &mut Evaluation)>,
+    mut signals: ResMut<Signals>,
+    mut writer: EventWriter<FragmentEndEvent>,
+    mut counts: Local<HashMap<Cow<'static, str>, usize>>,
+    mut warned: Local<HashSet<Cow<'static, str>>>,
+) {
+    counts.clear();
+    for (barrier, _) in barriers.iter() {
+        let count = *counts.entry(barrier.name.clone()).or_insert(barrier.count);
+
+        if count != barrier.count && warned.insert(barrier.name.clone()) {
+            warn!(
+                "barriers named \"{}\" have different counts: {count} and {}",
+                barrier.name, barrier.count
+            );
+        }
+    }
+
+    let released: Vec<_> = barriers
+        .iter()
+        .filter(|(barrier, _)| signals.arrived(&barrier.name) >= barrier.count)
+        .map(|(barrier, _)| barrier.name.clone())
+        .collect();
+
+    for name in &released {
+        signals.arrived.remove(name);
+    }
+
+    for (mut barrier, mut eval) in barriers.iter_mut() {
+        if released.contains(&barrier.name)
+            && let Some(id) = barrier.waiting.take()
+        {
+            writer.write(FragmentEndEvent::new(id, false));
+        }
+
+        // barriers are only played once per arrival
+        if barrier.waiting.is_some() {
+            eval.merge(false.evaluate());
+        }
+    }
+}
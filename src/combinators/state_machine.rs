@@ -0,0 +1,407 @@
+use crate::fragment::children::IntoChildren;
+use crate::fragment::event::{BeginStage, EndStage, InsertEndUp, MapContext, MapFn, StageEvent};
+use crate::fragment::systems::{insert_fragment_system, FragmentSystemKind};
+use crate::fragment::{is_active_concurrent, Concurrent, DataLeaf, KeepData, Leaf, Reachable};
+use crate::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use std::borrow::Cow;
+
+/// A fragment that plays one of its named states at a time,
+/// moving between them through each state's transitions.
+pub struct StateMachineFragment<F> {
+    initial: Cow<'static, str>,
+    states: F,
+}
+
+/// A fragment that plays one of its named states at a time,
+/// moving between them through each state's transitions.
+///
+/// The machine begins in `initial` and keeps replaying the current
+/// state until one of its transitions is taken.
+/// ```ignore
+/// state_machine("idle", (
+///     machine_state("idle", idle_chatter()).to("greeting", player_nearby),
+///     machine_state("greeting", "Welcome, traveler!").to_on_end("haggling"),
+///     machine_state("haggling", haggle()).to_on_end_with(|line: &Dialogue| {
+///         (line.0 == "Deal!").then_some("farewell")
+///     }),
+///     machine_state("farewell", "Safe travels.").to_on_end("idle"),
+/// ))
+/// ```
+///
+/// The machine ends whenever a state ends without taking a transition.
+/// The current state is persisted with `save_as`.
+///
+/// An initial state or transition target that doesn't name one of
+/// the machine's states is reported, and that transition is never taken.
+pub fn state_machine<F>(
+    initial: impl Into<Cow<'static, str>>,
+    states: F,
+) -> StateMachineFragment<F> {
+    StateMachineFragment {
+        initial: initial.into(),
+        states,
+    }
+}
+
+/// A named state of a [state_machine].
+pub fn machine_state<F>(name: impl Into<Cow<'static, str>>, fragment: F) -> StateFragment<F> {
+    StateFragment {
+        name: name.into(),
+        fragment,
+        transitions: Vec::new(),
+        targets: Vec::new(),
+        keep_data: false,
+    }
+}
+
+type Transition = Box<dyn FnOnce(usize, FragmentId, &mut Commands) + Send>;
+
+/// A named state of a [state_machine].
+pub struct StateFragment<F> {
+    name: Cow<'static, str>,
+    fragment: F,
+    transitions: Vec<Transition>,
+    targets: Vec<Cow<'static, str>>,
+    /// Whether the state's leaves should keep their data for its transitions.
+    keep_data: bool,
+}
+
+impl<F> StateFragment<F> {
+    /// Move to `target` when `condition` returns true.
+    ///
+    /// Conditions are only checked while the state is current
+    /// and none of its leaves are active.
+    pub fn to<S, M>(mut self, target: impl Into<Cow<'static, str>>, condition: S) -> Self
+    where
+        S: IntoSystem<(), bool, M> + Send + 'static,
+    {
+        let target = target.into();
+        self.targets.push(target.clone());
+        self.transitions
+            .push(Box::new(move |index, fragment, commands| {
+                insert_fragment_system::<TransitionSystems, _, _, _, _>(
+                    commands,
+                    fragment,
+                    condition.map(move |taken: bool| taken.then(|| (index, target.clone()))),
+                );
+            }));
+        self
+    }
+
+    /// Move to `target` when the state ends.
+    pub fn to_on_end(mut self, target: impl Into<Cow<'static, str>>) -> Self {
+        let target = target.into();
+        self.targets.push(target.clone());
+        self.transitions
+            .push(Box::new(move |index, fragment, commands| {
+                commands
+                    .entity(fragment.entity())
+                    .insert_end_up(move |event, world| {
+                        if event.stage == EndStage::End {
+                            request_transition(world, fragment, index, target.clone());
+                        }
+                    });
+            }));
+        self
+    }
+
+    /// Move to the state returned by `transition` when the state ends.
+    ///
+    /// `transition` is given the data of the leaf event that ended the state.
+    pub fn to_on_end_with<D, T>(mut self, transition: T) -> Self
+    where
+        D: Threaded,
+        T: Fn(&D) -> Option<&'static str> + Send + Sync + 'static,
+    {
+        self.keep_data = true;
+        self.transitions
+            .push(Box::new(move |index, fragment, commands| {
+                commands
+                    .entity(fragment.entity())
+                    .insert_end_up(move |event, world| {
+                        if event.stage != EndStage::End {
+                            return;
+                        }
+
+                        let target = world
+                            .get::<DataLeaf<D>>(event.id.fragment.entity())
+                            .and_then(|leaf| transition(leaf.get()));
+
+                        if let Some(target) = target
+                            && !request_transition(world, fragment, index, target.into())
+                        {
+                            warn!("state machine has no state named \"{target}\"");
+                        }
+                    });
+            }));
+        self
+    }
+}
+
+/// A state machine's current state.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(Fragment)]
+pub struct StateMachine {
+    current: Cow<'static, str>,
+}
+
+impl StateMachine {
+    /// The name of the current state.
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+}
+
+/// A state machine's state, along with the transition
+/// it will take once the machine updates.
+#[derive(Debug, Component)]
+pub struct MachineState {
+    name: Cow<'static, str>,
+    /// The targets of the state's transitions, where they're known up front.
+    targets: Vec<Cow<'static, str>>,
+    next: Option<(usize, Cow<'static, str>)>,
+}
+
+impl MachineState {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Whether a state's machine has a state named `target`.
+fn has_state(world: &World, fragment: Entity, target: &str) -> bool {
+    world
+        .get::<ChildOf>(fragment)
+        .and_then(|machine| world.get::<Children>(machine.parent()))
+        .is_some_and(|states| {
+            states.iter().any(|state| {
+                world
+                    .get::<MachineState>(state)
+                    .is_some_and(|state| state.name == target)
+            })
+        })
+}
+
+/// Queue a state's transition.
+///
+/// If several transitions are taken at once, the one declared first wins.
+/// Returns false if the machine has no state named `target`.
+fn request_transition(
+    world: &mut World,
+    fragment: FragmentId,
+    index: usize,
+    target: Cow<'static, str>,
+) -> bool {
+    if !has_state(world, fragment.entity(), &target) {
+        return false;
+    }
+
+    if let Some(mut state) = world.get_mut::<MachineState>(fragment.entity())
+        && state.next.as_ref().is_none_or(|(next, _)| index < *next)
+    {
+        state.next = Some((index, target));
+    }
+
+    true
+}
+
+/// Queues a state's transition when its condition returns true.
+pub(super) struct TransitionSystems;
+
+impl FragmentSystemKind<Option<(usize, Cow<'static, str>)>> for TransitionSystems {
+    type Gate = (&'static FragmentState, &'static Reachable);
+
+    fn should_run((state, reachable): (&FragmentState, &Reachable)) -> bool {
+        reachable.0 && state.active_events.is_empty()
+    }
+
+    fn apply(
+        world: &mut World,
+        fragment: FragmentId,
+        transition: Option<(usize, Cow<'static, str>)>,
+    ) {
+        let Some((index, target)) = transition else {
+            return;
+        };
+
+        if !request_transition(world, fragment, index, target) {
+            return;
+        }
+
+        // the machine moves on next frame, so don't replay the state in the meantime
+        if let Some(mut evaluation) = world.get_mut::<Evaluation>(fragment.entity()) {
+            evaluation.merge(false.evaluate());
+        }
+    }
+}
+
+/// Take any queued transitions and evaluate only the current state to true.
+pub(super) fn update_state_machines(
+    mut machines: Query<(&mut StateMachine, &Children)>,
+    mut states: Query<(
+        &mut MachineState,
+        &mut Evaluation,
+        &FragmentState,
+        Has<Concurrent>,
+    )>,
+) {
+    for (mut machine, children) in machines.iter_mut() {
+        for child in children.iter() {
+            let Ok((mut state, _, fragment, ..)) = states.get_mut(child) else {
+                continue;
+            };
+
+            if state.name != machine.current || !fragment.active_events.is_empty() {
+                continue;
+            }
+
+            if let Some((_, next)) = state.next.take() {
+                machine.current = next;
+                break;
+            }
+        }
+
+        for child in children.iter() {
+            let Ok((mut state, mut eval, fragment, concurrent)) = states.get_mut(child) else {
+                continue;
+            };
+
+            // transitions queued by states that are no longer current are dropped
+            let current = state.name == machine.current;
+            if !current && state.next.is_some() {
+                state.next = None;
+            }
+
+            if current && is_active_concurrent(fragment, concurrent) {
+                continue;
+            }
+
+            let active = !fragment.active_events.is_empty();
+
+            eval.merge((current && !active).evaluate());
+        }
+    }
+}
+
+fn map_begin(world: &World, input: MapContext<BeginStage>) -> StageEvent<BeginStage> {
+    let running = world
+        .get::<FragmentState>(input.target)
+        .is_some_and(|state| state.active);
+
+    StageEvent {
+        id: input.event.id,
+        stage: if running {
+            BeginStage::Visit
+        } else {
+            input.event.stage
+        },
+    }
+}
+
+fn map_end(world: &World, input: MapContext<EndStage>) -> StageEvent<EndStage> {
+    // the machine keeps running while the ending state has a transition to take
+    let transition = input.child.is_some_and(|child| {
+        world
+            .get::<MachineState>(child)
+            .is_some_and(|state| state.next.is_some())
+    });
+
+    StageEvent {
+        id: input.event.id,
+        stage: if transition && input.event.stage == EndStage::End {
+            EndStage::Visit
+        } else {
+            input.event.stage
+        },
+    }
+}
+
+/// Mark every leaf within a state to keep its data.
+fn keep_data(world: &mut World, fragment: Entity) {
+    if world.get::<Leaf>(fragment).is_some() {
+        world.entity_mut(fragment).insert(KeepData);
+    }
+
+    let children = world
+        .get::<Children>(fragment)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+    for child in children {
+        keep_data(world, child);
+    }
+}
+
+impl<Data, C, F> IntoFragment<Data, C> for StateFragment<F>
+where
+    Data: Threaded,
+    F: IntoFragment<Data, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let id = self.fragment.into_fragment(context, commands);
+        commands.entity(id.entity()).insert(MachineState {
+            name: self.name,
+            targets: self.targets,
+            next: None,
+        });
+
+        for (index, transition) in self.transitions.into_iter().enumerate() {
+            transition(index, id, commands);
+        }
+
+        if self.keep_data {
+            commands.queue(move |world: &mut World| keep_data(world, id.entity()));
+        }
+
+        id
+    }
+}
+
+impl<Data, C, F> IntoFragment<Data, C> for StateMachineFragment<F>
+where
+    Data: Threaded,
+    F: IntoChildren<Data, C>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let states = self.states.into_children(context, commands);
+
+        let initial = self.initial.clone();
+        let entities = states.as_ref().to_vec();
+        commands.queue(move |world: &mut World| {
+            let states: Vec<_> = entities
+                .iter()
+                .filter_map(|state| world.get::<MachineState>(*state))
+                .collect();
+            let known = |name: &str| states.iter().any(|state| state.name == name);
+
+            if !known(&initial) {
+                warn!("state machine has no initial state named \"{initial}\"");
+            }
+
+            for state in &states {
+                for target in state.targets.iter().filter(|target| !known(target)) {
+                    warn!(
+                        "state \"{}\" has a transition to \"{target}\", which isn't a state",
+                        state.name
+                    );
+                }
+            }
+        });
+
+        FragmentId::new(
+            commands
+                .spawn((
+                    StateMachine {
+                        current: self.initial,
+                    },
+                    MapFn::world_function(map_begin),
+                    MapFn::world_function(map_end),
+                ))
+                .add_children(states.as_ref())
+                .id(),
+        )
+    }
+}
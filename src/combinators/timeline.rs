@@ -0,0 +1,330 @@
+use super::select::IntoKeyedChildren;
+use super::sequence::SequenceCursor;
+use crate::clock::SequenceClock;
+use crate::fragment::event::{
+    skip_recursive, BeginStage, EndStage, InsertBeginDown, MapContext, MapFn, StageEvent,
+};
+use crate::fragment::{is_active_concurrent, Concurrent, Reachable};
+use crate::prelude::*;
+use bevy_ecs::prelude::*;
+use std::time::Duration;
+
+/// A fragment whose children begin at fixed offsets from its start.
+pub struct TimelineFragment<T> {
+    tracks: T,
+}
+
+/// A fragment whose children begin at fixed offsets from its start.
+///
+/// Each track pairs an offset with a fragment. Tracks begin once their
+/// offset has elapsed on the [`SequenceClock`], whether or not earlier
+/// tracks are still active, and the timeline ends once every track has ended.
+/// ```ignore
+/// use bevy_sequence::combinators::timeline::secs;
+///
+/// timeline((
+///     (secs(0.0), camera_pan()),
+///     (secs(2.5), "Look over there!"),
+///     (secs(4.0), sfx("thunder")),
+/// ))
+/// ```
+///
+/// Offsets are measured from when the timeline is first reached, so
+/// a timeline whose earliest track has a non-zero offset waits before
+/// beginning it. Use [`seek_timeline`] to jump ahead.
+pub fn timeline<T>(tracks: T) -> TimelineFragment<T> {
+    TimelineFragment { tracks }
+}
+
+/// Shorthand for [`Duration::from_secs_f32`].
+pub fn secs(secs: f32) -> Duration {
+    Duration::from_secs_f32(secs)
+}
+
+#[derive(Debug, Default, Component)]
+#[require(Fragment, SequenceCursor, Concurrent)]
+pub struct Timeline {
+    /// The [`SequenceClock`] time and the timeline's position when it
+    /// was first reached or was seeked.
+    anchor: Option<(Duration, Duration)>,
+    /// Whether the anchor was set in the current pass.
+    running: bool,
+    /// The position the next pass starts from.
+    start: Duration,
+}
+
+/// A timeline track's offset from the timeline's start.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct TimelineTrack(pub Duration);
+
+/// Move a timeline to `time`.
+///
+/// Tracks before `time` that haven't begun in this pass are skipped by
+/// running their end hooks, and the remaining tracks are rescheduled
+/// from `time`. Active tracks keep playing. If the timeline isn't
+/// running, its next pass starts from `time` instead.
+///
+/// `time` is clamped to the last track's offset, so seeking past the end
+/// begins the last track immediately, leaving a track to end the timeline.
+/// Seeking backwards doesn't replay tracks that have already played in
+/// this pass.
+pub fn seek_timeline(fragment: FragmentId, time: Duration, commands: &mut Commands) {
+    commands.queue(move |world: &mut World| {
+        let Some((tracks, running)) = timeline_tracks(world, fragment.entity()) else {
+            return;
+        };
+
+        let last = tracks.iter().map(|track| track.offset).max();
+        let time = last.map_or(time, |last| time.min(last));
+
+        for track in tracks {
+            if track.offset < time && track.is_pending() {
+                skip_recursive(track.entity, world);
+            }
+        }
+
+        let now = world.resource::<SequenceClock>().elapsed();
+        if let Some(mut timeline) = world.get_mut::<Timeline>(fragment.entity()) {
+            if !running {
+                timeline.start = time;
+            }
+
+            // a timeline waiting for its first track waits from `time` as well
+            if running || timeline.anchor.is_some() {
+                timeline.anchor = Some((now, time));
+            }
+        }
+    });
+}
+
+/// A timeline track's progress in the timeline's current pass.
+struct TrackStatus {
+    entity: Entity,
+    offset: Duration,
+    done: bool,
+    active: bool,
+}
+
+impl TrackStatus {
+    fn is_pending(&self) -> bool {
+        !self.done && !self.active
+    }
+}
+
+/// The timeline's tracks and whether it's running.
+fn timeline_tracks(world: &World, timeline: Entity) -> Option<(Vec<TrackStatus>, bool)> {
+    let timeline = world.get_entity(timeline).ok()?;
+    let state = timeline.get::<FragmentState>()?;
+    let cursor = timeline.get::<SequenceCursor>()?;
+    let tracks = timeline
+        .get::<Children>()?
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entity)| {
+            let offset = world.get::<TimelineTrack>(entity)?.0;
+            let track = world.get::<FragmentState>(entity);
+
+            Some(TrackStatus {
+                entity,
+                offset,
+                done: track.is_none_or(|track| cursor.is_done(i, track, state.completed)),
+                active: track.is_some_and(|track| !track.active_events.is_empty()),
+            })
+        })
+        .collect();
+
+    Some((tracks, state.active))
+}
+
+pub(super) fn update_timeline_tracks(
+    mut timelines: Query<(
+        Entity,
+        &mut Timeline,
+        &SequenceCursor,
+        &Reachable,
+        &Children,
+    )>,
+    mut tracks: Query<(
+        &mut Evaluation,
+        &FragmentState,
+        &TimelineTrack,
+        Has<Concurrent>,
+    )>,
+    states: Query<&FragmentState>,
+    clock: Res<SequenceClock>,
+) {
+    for (entity, mut timeline, cursor, reachable, children) in timelines.iter_mut() {
+        let Ok(outer) = states.get(entity) else {
+            continue;
+        };
+
+        let is_pending = |index: usize, state: &FragmentState| {
+            state.active_events.is_empty() && !cursor.is_done(index, state, outer.completed)
+        };
+
+        // a timeline that isn't running starts waiting for its
+        // first track once it's reached
+        if !outer.active {
+            if timeline.running {
+                timeline.running = false;
+                timeline.anchor = None;
+            }
+
+            if !reachable.0 {
+                timeline.anchor = None;
+            } else if timeline.anchor.is_none() {
+                timeline.anchor = Some((clock.elapsed(), timeline.start));
+            }
+        }
+
+        let now = timeline.anchor.map_or(timeline.start, |(began, position)| {
+            clock.elapsed().saturating_sub(began) + position
+        });
+
+        for (i, child) in children.iter().enumerate() {
+            let Ok((mut eval, state, track, concurrent)) = tracks.get_mut(child) else {
+                continue;
+            };
+
+            if is_pending(i, state) && track.0 <= now {
+                eval.merge(true.evaluate());
+                continue;
+            }
+
+            if is_active_concurrent(state, concurrent) {
+                continue;
+            }
+
+            eval.merge(false.evaluate());
+        }
+    }
+}
+
+fn map_begin(world: &World, input: MapContext<BeginStage>) -> StageEvent<BeginStage> {
+    let running = world
+        .get::<FragmentState>(input.target)
+        .is_some_and(|state| state.active);
+
+    StageEvent {
+        id: input.event.id,
+        stage: if running {
+            BeginStage::Visit
+        } else {
+            input.event.stage
+        },
+    }
+}
+
+fn map_end(world: &World, input: MapContext<EndStage>) -> StageEvent<EndStage> {
+    // the timeline ends once every track has ended in this pass
+    let done = timeline_tracks(world, input.target)
+        .is_some_and(|(tracks, _)| tracks.iter().all(|track| track.done && !track.active));
+
+    StageEvent {
+        id: input.event.id,
+        stage: if done || input.event.stage != EndStage::End {
+            input.event.stage
+        } else {
+            EndStage::Visit
+        },
+    }
+}
+
+impl<Data, C, T> IntoFragment<Data, C> for TimelineFragment<T>
+where
+    Data: Threaded,
+    T: IntoKeyedChildren<Data, C, Duration>,
+{
+    fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId {
+        let (offsets, children) = self.tracks.into_keyed_children(context, commands);
+
+        for (child, offset) in children.iter().zip(offsets) {
+            commands.entity(*child).insert(TimelineTrack(offset));
+        }
+
+        let mut timeline = commands.spawn((
+            Timeline::default(),
+            MapFn::world_function(map_begin),
+            MapFn::world_function(map_end),
+        ));
+
+        let entity = timeline.id();
+        timeline
+            .insert_begin_down(move |stage, world| {
+                if stage.stage != BeginStage::Start {
+                    return;
+                }
+
+                // the timeline keeps the position it's been waiting from
+                let now = world.resource::<SequenceClock>().elapsed();
+                if let Some(mut timeline) = world.get_mut::<Timeline>(entity) {
+                    let start = std::mem::take(&mut timeline.start);
+                    timeline.anchor.get_or_insert((now, start));
+                    timeline.running = true;
+                }
+            })
+            .add_children(&children);
+
+        FragmentId::new(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::secs;
+    use crate::prelude::*;
+    use crate::test_app::{app, run, spawn};
+    use bevy_app::App;
+
+    fn seek(app: &mut App, timeline: FragmentId, time: f32) {
+        let world = app.world_mut();
+        seek_timeline(timeline, secs(time), &mut world.commands());
+        world.flush();
+    }
+
+    #[test]
+    fn tracks_play_at_their_offsets() {
+        let mut app = app();
+        spawn(
+            &mut app,
+            timeline(((secs(0.0), "a"), (secs(1.0), "b"), (secs(2.0), "c")))
+                .always()
+                .once(),
+        );
+
+        assert_eq!(run(&mut app, 2), ["a"]);
+        assert_eq!(run(&mut app, 10), ["a", "b"]);
+        assert_eq!(run(&mut app, 10), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn seeking_before_a_pass_starts_it_from_there() {
+        let mut app = app();
+        let id = spawn(
+            &mut app,
+            timeline(((secs(0.0), "a"), (secs(1.0), "b"), (secs(2.0), "c")))
+                .always()
+                .once(),
+        );
+        seek(&mut app, id, 1.5);
+
+        assert!(run(&mut app, 2).is_empty());
+        assert_eq!(run(&mut app, 10), ["c"]);
+    }
+
+    #[test]
+    fn seeking_while_running_skips_the_tracks_in_between() {
+        let mut app = app();
+        let id = spawn(
+            &mut app,
+            timeline(((secs(0.0), "a"), (secs(1.0), "b"), (secs(2.0), "c")))
+                .always()
+                .once(),
+        );
+
+        assert_eq!(run(&mut app, 2), ["a"]);
+        seek(&mut app, id, 1.5);
+        assert_eq!(run(&mut app, 2), ["a"]);
+        assert_eq!(run(&mut app, 10), ["a", "c"]);
+    }
+}
@@ -0,0 +1,76 @@
+//! Built-in fragment evaluations based on [`FragmentState`].
+//!
+//! These take the evaluated fragment's ID, so they're used with
+//! [`FragmentExt::eval_id`](crate::combinators::FragmentExt::eval_id).
+//!
+//! ```ignore
+//! "Back so soon?"
+//!     .eval_id(visited_at_least(3))
+//!     .eval_id(not_played_within(Duration::from_secs(600)))
+//! ```
+
+use crate::{clock::SequenceClock, prelude::*};
+use bevy_ecs::prelude::*;
+use std::time::Duration;
+
+/// An evaluation system reading the evaluated fragment's [`FragmentState`].
+pub trait StateCondition:
+    FnMut(In<FragmentId>, Query<&FragmentState>, Res<SequenceClock>) -> bool + Send + Sync + 'static
+{
+}
+
+impl<F> StateCondition for F where
+    F: FnMut(In<FragmentId>, Query<&FragmentState>, Res<SequenceClock>) -> bool
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+fn state_condition(
+    condition: impl Fn(&FragmentState, &SequenceClock) -> bool + Send + Sync + 'static,
+) -> impl StateCondition {
+    move |fragment: In<FragmentId>, states: Query<&FragmentState>, clock: Res<SequenceClock>| {
+        states
+            .get(fragment.entity())
+            .is_ok_and(|state| condition(state, &clock))
+    }
+}
+
+/// Evaluate true if the fragment started within the last `duration`.
+pub fn played_within(duration: Duration) -> impl StateCondition {
+    state_condition(move |state, clock| {
+        state
+            .last_begin
+            .is_some_and(|begin| clock.elapsed().saturating_sub(begin) < duration)
+    })
+}
+
+/// Evaluate true if the fragment hasn't started within the last `duration`.
+///
+/// Fragments that have never started evaluate true.
+pub fn not_played_within(duration: Duration) -> impl StateCondition {
+    state_condition(move |state, clock| {
+        state
+            .last_begin
+            .is_none_or(|begin| clock.elapsed().saturating_sub(begin) >= duration)
+    })
+}
+
+/// Evaluate true if the fragment has been visited at least `n` times.
+///
+/// A fragment is visited each time it or any fragment within it begins,
+/// so a sequence is visited once for each item it plays.
+pub fn visited_at_least(n: usize) -> impl StateCondition {
+    state_condition(move |state, _| state.visited >= n)
+}
+
+/// Evaluate true if the fragment has completed at least `n` times.
+pub fn completed_at_least(n: usize) -> impl StateCondition {
+    state_condition(move |state, _| state.completed >= n)
+}
+
+/// Evaluate true if the fragment has been interrupted at least `n` times.
+pub fn interrupted_at_least(n: usize) -> impl StateCondition {
+    state_condition(move |state, _| state.interrupted >= n)
+}
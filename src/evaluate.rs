@@ -1,69 +1,113 @@
 use crate::fragment::FragmentId;
-use bevy_ecs::prelude::*;
+use bevy_ecs::{
+    prelude::*,
+    system::{Deferred, SystemBuffer, SystemMeta, SystemParam},
+};
 use std::collections::{hash_map::Entry, HashMap};
 
-pub trait Evaluate: sealed::Sealed {
+/// Types that can be converted into an [`Evaluation`].
+///
+/// Any system whose output implements this trait can be used
+/// as a fragment evaluation.
+pub trait Evaluate {
     fn evaluate(&self) -> Evaluation;
 }
 
-mod sealed {
-    pub trait Sealed {}
-
-    impl Sealed for super::Evaluation {}
-    impl<const LEN: usize> Sealed for [bool; LEN] {}
-    impl Sealed for Vec<bool> {}
-    impl Sealed for bool {}
-}
-
 impl Evaluate for bool {
     fn evaluate(&self) -> Evaluation {
         Evaluation {
             result: Some(*self),
             count: 1,
+            score: None,
         }
     }
 }
 
-impl<const LEN: usize> Evaluate for [bool; LEN] {
+/// A bare score has no opinion on whether the fragment should run.
+impl Evaluate for f32 {
     fn evaluate(&self) -> Evaluation {
-        let result = if LEN == 0 {
-            None
-        } else {
-            Some(self.iter().all(|e| *e))
-        };
-
         Evaluation {
-            result,
-            count: self.len(),
+            result: None,
+            count: 0,
+            score: Some(*self),
         }
     }
 }
 
-impl Evaluate for Vec<bool> {
+/// `None` has no opinion on whether the fragment should run.
+impl<T: Evaluate> Evaluate for Option<T> {
     fn evaluate(&self) -> Evaluation {
-        let result = if self.is_empty() {
-            None
-        } else {
-            Some(self.iter().all(|e| *e))
-        };
+        self.as_ref().map(T::evaluate).unwrap_or_default()
+    }
+}
 
-        Evaluation {
-            result,
-            count: self.len(),
+/// Errors are logged and evaluate to false.
+impl<T: Evaluate, E: core::fmt::Display> Evaluate for Result<T, E> {
+    fn evaluate(&self) -> Evaluation {
+        match self {
+            Ok(value) => value.evaluate(),
+            Err(e) => {
+                bevy_log::error!("fragment evaluation failed: {e}");
+                false.evaluate()
+            }
         }
     }
 }
 
+impl<T: Evaluate + ?Sized> Evaluate for &T {
+    fn evaluate(&self) -> Evaluation {
+        T::evaluate(self)
+    }
+}
+
+/// All items must be true. An empty slice has no opinion.
+impl<T: Evaluate> Evaluate for [T] {
+    fn evaluate(&self) -> Evaluation {
+        self.iter().collect()
+    }
+}
+
+impl<T: Evaluate, const LEN: usize> Evaluate for [T; LEN] {
+    fn evaluate(&self) -> Evaluation {
+        self.as_slice().evaluate()
+    }
+}
+
+impl<T: Evaluate> Evaluate for Vec<T> {
+    fn evaluate(&self) -> Evaluation {
+        self.as_slice().evaluate()
+    }
+}
+
+macro_rules! tuple_evaluate {
+    ($($ty:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($ty: Evaluate),*> Evaluate for ($($ty,)*) {
+            fn evaluate(&self) -> Evaluation {
+                let ($($ty,)*) = self;
+                Evaluation::default() $(& $ty.evaluate())*
+            }
+        }
+    };
+}
+
+variadics_please::all_tuples!(tuple_evaluate, 1, 15, T);
+
 impl Evaluate for Evaluation {
     fn evaluate(&self) -> Evaluation {
         *self
     }
 }
 
-#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
 pub struct Evaluation {
     pub result: Option<bool>,
     pub count: usize,
+    /// An optional utility score.
+    ///
+    /// Scores are summed as evaluations are merged, so a leaf's
+    /// score is the total of every score on the path from its root.
+    pub score: Option<f32>,
 }
 
 impl Evaluation {
@@ -72,27 +116,45 @@ impl Evaluation {
     }
 }
 
+/// Merge every item, so all items must be true.
+///
+/// This allows iterators of evaluations, like `bool`s,
+/// to be collected into a single evaluation.
+impl<E: Evaluate> FromIterator<E> for Evaluation {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Evaluation::default(), |acc, e| acc & e.evaluate())
+    }
+}
+
 impl core::ops::BitAnd for Evaluation {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
+        let score = match (self.score, rhs.score) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+
         match (self.result, rhs.result) {
             (Some(a), Some(b)) => Self {
                 result: Some(a && b),
                 count: self.count + rhs.count,
+                score,
             },
-            (None, Some(_)) => rhs,
-            (Some(_), None) | (None, None) => self,
+            (None, Some(_)) => Self { score, ..rhs },
+            (Some(_), None) | (None, None) => Self { score, ..self },
         }
     }
 }
 
-#[derive(Resource, Debug, Default)]
+/// A buffer of evaluations that are merged into their
+/// fragments when the buffer is applied.
+#[derive(Debug, Default)]
 pub struct EvaluatedFragments {
     pub(super) evaluations: HashMap<FragmentId, Evaluation>,
 }
 
-#[allow(unused)]
 impl EvaluatedFragments {
     pub fn insert<E: Evaluate>(&mut self, id: FragmentId, evaluation: E) {
         let eval = evaluation.evaluate();
@@ -122,3 +184,47 @@ impl EvaluatedFragments {
         self.evaluations.clear();
     }
 }
+
+impl SystemBuffer for EvaluatedFragments {
+    fn apply(&mut self, _: &SystemMeta, world: &mut World) {
+        for (id, evaluation) in self.evaluations.drain() {
+            if let Some(mut eval) = world.get_mut::<Evaluation>(id.entity()) {
+                eval.merge(evaluation);
+            }
+        }
+    }
+}
+
+/// Add evaluations to fragments from ordinary systems.
+///
+/// Evaluations are buffered and merged into their fragments once
+/// [`SequenceSets::Evaluate`] completes, so any number of systems
+/// can write evaluations in parallel.
+///
+/// ```ignore
+/// fn in_range(
+///     npcs: Query<(&Transform, &Bark)>,
+///     player: Single<&Transform, With<Player>>,
+///     mut writer: EvaluationWriter,
+/// ) {
+///     for (transform, bark) in &npcs {
+///         let distance = transform.translation.distance(player.translation);
+///         writer.insert(bark.fragment, distance < 10.0);
+///     }
+/// }
+///
+/// app.add_systems(PreUpdate, in_range.in_set(SequenceSets::Evaluate));
+/// ```
+///
+/// [`SequenceSets::Evaluate`]: crate::SequenceSets::Evaluate
+#[derive(SystemParam)]
+pub struct EvaluationWriter<'s> {
+    evaluations: Deferred<'s, EvaluatedFragments>,
+}
+
+impl EvaluationWriter<'_> {
+    /// Merge an evaluation into the fragment's evaluation.
+    pub fn insert<E: Evaluate>(&mut self, id: FragmentId, evaluation: E) {
+        self.evaluations.insert(id, evaluation);
+    }
+}
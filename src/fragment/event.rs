@@ -1,5 +1,5 @@
 use super::{FragmentState, Root, SelectedFragments};
-use crate::prelude::FragmentId;
+use crate::{clock::SequenceClock, prelude::FragmentId};
 use bevy_ecs::{component::Mutable, prelude::*, system::SystemId};
 use std::{
     marker::PhantomData,
@@ -96,6 +96,12 @@ pub struct FragmentEndEvent {
     interruption: bool,
 }
 
+impl FragmentEndEvent {
+    pub(crate) fn new(id: IdPair, interruption: bool) -> Self {
+        Self { id, interruption }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Component)]
 #[component(storage = "SparseSet")]
 pub struct StageEvent<Stage> {
@@ -185,13 +191,8 @@ callback!(OnBeginDown, BeginStage, InsertBeginDown, insert_begin_down);
 callback!(OnEndUp, EndStage, InsertEndUp, insert_end_up);
 callback!(OnEndDown, EndStage, InsertEndDown, insert_end_down);
 
-#[derive(Clone, Component)]
+#[derive(Clone, Default, Component)]
 pub struct OnInterruptUp(pub Vec<Arc<Mutex<dyn FnMut(&mut World) + Send + Sync + 'static>>>);
-impl Default for OnInterruptUp {
-    fn default() -> Self {
-        OnInterruptUp(Vec::new())
-    }
-}
 
 pub trait InsertOnInterrupt {
     fn insert_interrupt<F>(&mut self, hook: F) -> &mut Self
@@ -220,6 +221,9 @@ pub struct MapContext<Stage> {
 #[derive(Clone)]
 pub enum MapFn<Stage: 'static> {
     Function(Arc<dyn Fn(MapContext<Stage>) -> StageEvent<Stage> + Send + Sync + 'static>),
+    WorldFunction(
+        Arc<dyn Fn(&World, MapContext<Stage>) -> StageEvent<Stage> + Send + Sync + 'static>,
+    ),
     System(SystemId<In<MapContext<Stage>>, StageEvent<Stage>>),
 }
 
@@ -227,6 +231,7 @@ impl<Stage> MapFn<Stage> {
     fn call(&self, world: &mut World, context: MapContext<Stage>) -> StageEvent<Stage> {
         match self {
             MapFn::Function(function) => function(context),
+            MapFn::WorldFunction(function) => function(world, context),
             MapFn::System(sys) => world.run_system_with(*sys, context).unwrap(),
         }
     }
@@ -239,6 +244,14 @@ impl<Stage: 'static> MapFn<Stage> {
     {
         Self::Function(Arc::new(function))
     }
+
+    /// Map events with read-only access to the world.
+    pub fn world_function<F>(function: F) -> Self
+    where
+        F: Fn(&World, MapContext<Stage>) -> StageEvent<Stage> + Send + Sync + 'static,
+    {
+        Self::WorldFunction(Arc::new(function))
+    }
 }
 
 impl<Stage: Send + 'static> Component for MapFn<Stage> {
@@ -295,19 +308,22 @@ fn begin_recursive(
         (system.lock().unwrap())(event, world);
     }
 
+    let now = world.get_resource::<SequenceClock>().map(|c| c.elapsed());
     let mut child = world.get_entity_mut(node).ok()?;
     let mut state = child.get_mut::<FragmentState>()?;
 
+    state.visited += 1;
     if matches!(event.stage, BeginStage::Start) {
         state.triggered += 1;
         state.active = true;
+        state.last_begin = now;
     }
     state.active_events.insert(event.id.event);
 
-    if root.is_none() {
-        if let Some(parent) = parent_id {
-            begin_recursive(parent, Some(node), event, world);
-        }
+    if root.is_none()
+        && let Some(parent) = parent_id
+    {
+        begin_recursive(parent, Some(node), event, world);
     }
 
     for system in on_begin_down.iter().flat_map(|o| o.0.iter()) {
@@ -340,7 +356,7 @@ pub(crate) fn begin_world(world: &mut World) {
     }
 }
 
-fn end_recursive(
+pub(crate) fn end_recursive(
     node: Entity,
     child_node: Option<Entity>,
     mut event: StageEvent<EndStage>,
@@ -384,6 +400,7 @@ fn end_recursive(
             (system.lock().unwrap())(event, world);
         }
 
+        let now = world.get_resource::<SequenceClock>().map(|c| c.elapsed());
         let mut child = world.get_entity_mut(node).ok()?;
         let mut state = child.get_mut::<FragmentState>()?;
 
@@ -391,8 +408,11 @@ fn end_recursive(
             EndStage::End => {
                 state.completed += 1;
                 state.active = false;
+                state.last_end = now;
             }
             EndStage::Interrupt => {
+                state.interrupted += 1;
+                state.last_end = now;
                 for system in interrupt.iter().flat_map(|o| o.0.iter()) {
                     (system.lock().unwrap())(world);
                 }
@@ -400,10 +420,10 @@ fn end_recursive(
             _ => {}
         }
 
-        if root.is_none() {
-            if let Some(parent) = parent_id {
-                end_recursive(parent, Some(node), event, world);
-            }
+        if root.is_none()
+            && let Some(parent) = parent_id
+        {
+            end_recursive(parent, Some(node), event, world);
         }
 
         for system in on_end_down.iter().flat_map(|o| o.0.iter()) {
@@ -414,6 +434,97 @@ fn end_recursive(
     Some(())
 }
 
+/// End a fragment and its descendants without playing them.
+///
+/// Every end hook in the subtree is run with [`EndStage::End`], children
+/// before their parents, and each fragment is counted as completed.
+/// Nothing above `node` is notified.
+pub(crate) fn skip_recursive(node: Entity, world: &mut World) {
+    let event = StageEvent {
+        stage: EndStage::End,
+        id: IdPair {
+            fragment: FragmentId::new(node),
+            event: EventId::new(),
+        },
+    };
+
+    skip_up(node, event, world);
+    skip_down(node, event, world);
+}
+
+fn skip_up(node: Entity, event: StageEvent<EndStage>, world: &mut World) {
+    let children = world
+        .get::<Children>(node)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+    for child in children {
+        skip_up(child, event, world);
+    }
+
+    let on_end = world.get::<OnEndUp>(node).cloned();
+    for system in on_end.iter().flat_map(|o| o.0.iter()) {
+        (system.lock().unwrap())(event, world);
+    }
+
+    let now = world.get_resource::<SequenceClock>().map(|c| c.elapsed());
+    if let Some(mut state) = world.get_mut::<FragmentState>(node) {
+        state.completed += 1;
+        state.active = false;
+        state.last_end = now;
+    }
+}
+
+fn skip_down(node: Entity, event: StageEvent<EndStage>, world: &mut World) {
+    let on_end_down = world.get::<OnEndDown>(node).cloned();
+    for system in on_end_down.iter().flat_map(|o| o.0.iter()) {
+        (system.lock().unwrap())(event, world);
+    }
+
+    let children = world
+        .get::<Children>(node)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+    for child in children {
+        skip_down(child, event, world);
+    }
+}
+
+/// Interrupt a single fragment whose current pass is abandoned.
+///
+/// The fragment's end hooks are run with [`EndStage::Interrupt`]
+/// along with its interrupt hooks. Nothing above `node` is notified.
+pub(crate) fn unwind_node(node: Entity, world: &mut World) {
+    let event = StageEvent {
+        stage: EndStage::Interrupt,
+        id: IdPair {
+            fragment: FragmentId::new(node),
+            event: EventId::new(),
+        },
+    };
+
+    let on_end = world.get::<OnEndUp>(node).cloned();
+    for system in on_end.iter().flat_map(|o| o.0.iter()) {
+        (system.lock().unwrap())(event, world);
+    }
+
+    let now = world.get_resource::<SequenceClock>().map(|c| c.elapsed());
+    if let Some(mut state) = world.get_mut::<FragmentState>(node) {
+        state.interrupted += 1;
+        state.active = false;
+        state.last_end = now;
+    }
+
+    let interrupt = world.get::<OnInterruptUp>(node).cloned();
+    for system in interrupt.iter().flat_map(|o| o.0.iter()) {
+        (system.lock().unwrap())(world);
+    }
+
+    let on_end_down = world.get::<OnEndDown>(node).cloned();
+    for system in on_end_down.iter().flat_map(|o| o.0.iter()) {
+        (system.lock().unwrap())(event, world);
+    }
+}
+
 pub(crate) fn end_world(mut reader: EventReader<FragmentEndEvent>, mut commands: Commands) {
     let end_events: Vec<_> = reader.read().copied().collect();
 
@@ -11,7 +11,16 @@ use bevy_ecs::prelude::*;
 #[require(Fragment)]
 pub struct Leaf;
 
+/// Marks a leaf that should keep the data of the events it emits
+/// as a [DataLeaf] component, so hooks can read it.
+#[derive(Debug, Default, Component)]
+pub(crate) struct KeepData;
+
 /// A leaf node that simply emits its contained value.
+///
+/// Leaves within a state machine's state that reads its leaves' data,
+/// like with `to_on_end_with`, keep their value as this component
+/// once they've begun.
 #[derive(Debug, Component)]
 #[require(Leaf)]
 pub struct DataLeaf<T>(T);
@@ -20,6 +29,10 @@ impl<T> DataLeaf<T> {
     pub fn new(value: T) -> Self {
         Self(value)
     }
+
+    pub fn get(&self) -> &T {
+        &self.0
+    }
 }
 
 impl<T, Data: Threaded, C> IntoFragment<Data, C> for DataLeaf<T>
@@ -37,6 +50,11 @@ where
         let id = commands
             .spawn(Leaf)
             .insert_begin_down(move |event, world| {
+                let leaf = event.id.fragment.entity();
+                if world.get::<KeepData>(leaf).is_some() {
+                    world.entity_mut(leaf).insert(DataLeaf(data.clone()));
+                }
+
                 world.send_event(FragmentEvent {
                     id: event.id,
                     data: data.clone(),
@@ -1,13 +1,23 @@
-use crate::combinators::or::OrItem;
+use crate::combinators::{
+    cond::Cond,
+    first_available::FirstAvailable,
+    or::OrItem,
+    sequence::{OptionalItem, Sequence},
+};
 use crate::evaluate::{Evaluate, Evaluation};
 use crate::Threaded;
-use bevy_ecs::prelude::*;
-use std::sync::{Arc, RwLock};
+use bevy_ecs::{entity::EntityHashSet, prelude::*, query::QueryData};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 pub mod children;
 pub mod event;
 mod leaf;
+pub(crate) mod systems;
 
+pub(crate) use leaf::KeepData;
 pub use leaf::{DataLeaf, Leaf};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component)]
@@ -30,20 +40,27 @@ pub trait IntoFragment<Data: Threaded, C = ()> {
     fn into_fragment(self, context: &Context<C>, commands: &mut Commands) -> FragmentId;
 }
 
-pub fn spawn_root<Data: Threaded>(fragment: impl IntoFragment<Data>, commands: &mut Commands) {
+pub fn spawn_root<Data: Threaded>(
+    fragment: impl IntoFragment<Data>,
+    commands: &mut Commands,
+) -> FragmentId {
     let root = fragment.into_fragment(&Arc::new(RwLock::new(())), commands);
 
     commands.entity(root.0).insert(Root);
+
+    root
 }
 
 pub fn spawn_root_with<Data: Threaded, C>(
     fragment: impl IntoFragment<Data, C>,
     commands: &mut Commands,
     context: C,
-) {
+) -> FragmentId {
     let root = fragment.into_fragment(&Arc::new(RwLock::new(context)), commands);
 
     commands.entity(root.0).insert(Root);
+
+    root
 }
 
 #[derive(Debug, Component, Default, Clone, PartialEq, Eq)]
@@ -52,6 +69,22 @@ pub fn spawn_root_with<Data: Threaded, C>(
 pub struct FragmentState {
     pub triggered: usize,
     pub completed: usize,
+    /// The number of times this fragment was interrupted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interrupted: usize,
+    /// The number of times this fragment or any of its descendants began.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub visited: usize,
+    /// The [`SequenceClock`] time when this fragment last started.
+    ///
+    /// [`SequenceClock`]: crate::clock::SequenceClock
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub last_begin: Option<Duration>,
+    /// The [`SequenceClock`] time when this fragment last ended or was interrupted.
+    ///
+    /// [`SequenceClock`]: crate::clock::SequenceClock
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub last_end: Option<Duration>,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub active: bool,
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -60,43 +93,216 @@ pub struct FragmentState {
 
 /// An entity representing a sequence fragment.
 #[derive(Debug, Default, Component)]
-#[require(Evaluation, FragmentState)]
+#[require(Evaluation, SettledEvaluation, FragmentState, Reachable, Blocked)]
 pub struct Fragment;
 
+/// A fragment whose children can begin while it's active,
+/// like a sequence's overlapping items or a timeline's tracks.
+#[derive(Debug, Default, Component)]
+pub struct Concurrent;
+
+/// Whether a fragment is active and concurrent, so its
+/// children are still walked and evaluated while it plays.
+pub(crate) fn is_active_concurrent(state: &FragmentState, concurrent: bool) -> bool {
+    concurrent && !state.active_events.is_empty()
+}
+
 /// A root fragment.
 #[derive(Debug, Default, Component, Clone)]
-#[require(Fragment)]
+#[require(Fragment, CachedLeaves)]
 pub struct Root;
 
-pub(crate) fn clear_evals(mut evals: Query<&mut Evaluation>) {
+/// The leaves found the last time a root's tree was walked.
+#[derive(Debug, Default, Component)]
+pub struct CachedLeaves {
+    leaves: Vec<(Entity, Evaluation)>,
+    dirty: bool,
+}
+
+/// A fragment's evaluation once every evaluation system has run.
+///
+/// This only changes when the final evaluation differs from the
+/// previous frame's, allowing unchanged trees to skip selection.
+#[derive(Debug, Default, Component, Clone, Copy, PartialEq)]
+pub(crate) struct SettledEvaluation(Evaluation);
+
+/// Whether a fragment can be reached when walking its tree.
+///
+/// A fragment is unreachable if it or any of its ancestors is evaluated
+/// to false by the tree's structure, like a sequence waiting on another
+/// item or an exhausted limit. Evaluation systems aren't run for
+/// unreachable fragments.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct Reachable(pub bool);
+
+impl Default for Reachable {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Whether a fragment's own evaluation made it unreachable
+/// the last time reachability was updated.
+#[derive(Debug, Default, Component, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Blocked(bool);
+
+/// Update the reachability of fragments below any fragment that
+/// became blocked or unblocked, or that moved to another parent.
+///
+/// A fragment's children are only revisited when its own reachability
+/// changes, so unchanged subtrees aren't walked.
+pub(crate) fn update_reachable(
+    mut evals: Query<
+        (
+            Entity,
+            &Evaluation,
+            &mut Blocked,
+            Option<&ChildOf>,
+            Has<Root>,
+        ),
+        Changed<Evaluation>,
+    >,
+    moved: Query<
+        (Entity, Option<&ChildOf>, Has<Root>),
+        (With<Fragment>, Or<(Changed<ChildOf>, Added<Root>)>),
+    >,
+    mut fragments: Query<(Option<&Children>, &Evaluation, &mut Reachable)>,
+    mut changed: Local<Vec<(Entity, Option<Entity>, bool)>>,
+    mut stack: Local<Vec<(Entity, bool)>>,
+) {
+    for (entity, eval, mut blocked, parent, root) in evals.iter_mut() {
+        if blocked.set_if_neq(Blocked(eval.result == Some(false))) {
+            changed.push((entity, parent.map(ChildOf::parent), root));
+        }
+    }
+    changed.extend(
+        moved
+            .iter()
+            .map(|(entity, parent, root)| (entity, parent.map(ChildOf::parent), root)),
+    );
+
+    for (entity, parent, root) in changed.drain(..) {
+        // fragments outside of a tree aren't walked
+        let parent_reachable = match parent {
+            Some(parent) => fragments
+                .get(parent)
+                .is_ok_and(|(.., reachable)| reachable.0),
+            None if root => true,
+            None => continue,
+        };
+        stack.push((entity, parent_reachable));
+
+        while let Some((node, parent_reachable)) = stack.pop() {
+            let Ok((children, eval, mut reachable)) = fragments.get_mut(node) else {
+                continue;
+            };
+
+            let is_reachable = parent_reachable && eval.result != Some(false);
+            if !reachable.set_if_neq(Reachable(is_reachable)) {
+                continue;
+            }
+
+            stack.extend(
+                children
+                    .iter()
+                    .flat_map(|c| c.iter())
+                    .map(|c| (c, is_reachable)),
+            );
+        }
+    }
+}
+
+pub(crate) fn clear_evals(mut evals: Query<&mut Evaluation, Changed<Evaluation>>) {
     for mut eval in evals.iter_mut() {
-        *eval = Default::default();
+        eval.set_if_neq(Default::default());
+    }
+}
+
+pub(crate) fn settle_evals(
+    mut evals: Query<(&Evaluation, &mut SettledEvaluation), Changed<Evaluation>>,
+) {
+    for (eval, mut settled) in evals.iter_mut() {
+        settled.set_if_neq(SettledEvaluation(*eval));
     }
 }
 
+/// Mark every root above a changed fragment as needing a new walk.
+///
+/// Fragments that lose their last child or are detached from their
+/// parent are treated as changed, since removals aren't caught by
+/// change detection.
+pub(crate) fn mark_dirty_roots(
+    changed: Query<
+        Entity,
+        Or<(
+            Changed<SettledEvaluation>,
+            Changed<FragmentState>,
+            Changed<Children>,
+            Changed<ChildOf>,
+            Changed<OptionalItem>,
+        )>,
+    >,
+    mut removed_children: RemovedComponents<Children>,
+    mut removed_parents: RemovedComponents<ChildOf>,
+    mut nodes: Query<(Option<&ChildOf>, Option<&mut CachedLeaves>)>,
+    mut visited: Local<EntityHashSet>,
+) {
+    visited.clear();
+
+    let removed = removed_children.read().chain(removed_parents.read());
+    for entity in changed.iter().chain(removed) {
+        let mut node = Some(entity);
+        while let Some(current) = node {
+            // Everything above a visited node has already been marked.
+            if !visited.insert(current) {
+                break;
+            }
+
+            let Ok((parent, cache)) = nodes.get_mut(current) else {
+                break;
+            };
+
+            node = parent.map(|p| p.parent());
+            if let Some(mut cache) = cache {
+                cache.dirty = true;
+            }
+        }
+    }
+}
+
+/// The data read from each fragment when walking a tree.
+#[derive(QueryData)]
+pub struct WalkData {
+    eval: &'static Evaluation,
+    state: &'static FragmentState,
+    children: Option<&'static Children>,
+    leaf: Has<Leaf>,
+    or: Has<OrItem>,
+    first_available: Has<FirstAvailable>,
+    cond: Has<Cond>,
+    sequence: Has<Sequence>,
+    optional: Option<&'static OptionalItem>,
+    concurrent: Has<Concurrent>,
+}
+
 /// Recursively walk the tree depth-first, building
 /// up evaluations we go.
 fn descend_tree(
     node: Entity,
     evaluation: Evaluation,
-    fragments: &Query<(
-        &Evaluation,
-        Option<&Children>,
-        Option<&Leaf>,
-        Option<&OrItem>,
-    )>,
+    fragments: &Query<WalkData>,
     leaves: &mut Vec<(Entity, Evaluation)>,
     first_eval: &mut Option<Evaluation>,
 ) {
-    let Ok((eval, children, leaf, or)) = fragments.get(node) else {
+    let Ok(item) = fragments.get(node) else {
         return;
     };
 
-    let eval = match (*first_eval, or) {
-        (Some(first), Some(_)) if first.result.is_some() => {
-            *eval & (!first.result.unwrap_or_default()).evaluate()
+    let eval = match *first_eval {
+        Some(first) if item.or && first.result.is_some() => {
+            *item.eval & (!first.result.unwrap_or_default()).evaluate()
         }
-        _ => *eval,
+        _ => *item.eval,
     };
 
     let new_eval = eval & evaluation;
@@ -106,42 +312,192 @@ fn descend_tree(
     }
 
     if new_eval.result.unwrap_or_default() {
-        if leaf.is_some() {
+        let children = item.children.map(|c| &c[..]).unwrap_or_default();
+
+        if item.leaf {
             leaves.push((node, new_eval));
+        } else if item.first_available {
+            descend_first_available(children, new_eval, fragments, leaves);
+        } else if item.cond {
+            descend_cond(children, new_eval, fragments, leaves);
+        } else if item.sequence {
+            descend_sequence(children, new_eval, fragments, leaves);
         } else {
             let mut first_eval = None;
-            for child in children.iter().flat_map(|c| c.iter()) {
-                descend_tree(child, new_eval, fragments, leaves, &mut first_eval);
+            for child in children {
+                descend_tree(*child, new_eval, fragments, leaves, &mut first_eval);
             }
         }
     }
 }
 
+/// Walk only the first child that yields any leaves,
+/// or the active child if there is one.
+fn descend_first_available(
+    children: &[Entity],
+    evaluation: Evaluation,
+    fragments: &Query<WalkData>,
+    leaves: &mut Vec<(Entity, Evaluation)>,
+) {
+    let active = children
+        .iter()
+        .find(|child| fragments.get(**child).is_ok_and(|item| item.state.active));
+
+    if let Some(active) = active {
+        descend_tree(*active, evaluation, fragments, leaves, &mut None);
+        return;
+    }
+
+    for child in children {
+        let found = leaves.len();
+        descend_tree(*child, evaluation, fragments, leaves, &mut None);
+
+        if leaves.len() > found {
+            break;
+        }
+    }
+}
+
+/// Walk only the first child whose own evaluation is true,
+/// or the active child if there is one.
+fn descend_cond(
+    children: &[Entity],
+    evaluation: Evaluation,
+    fragments: &Query<WalkData>,
+    leaves: &mut Vec<(Entity, Evaluation)>,
+) {
+    let active = children
+        .iter()
+        .find(|child| fragments.get(**child).is_ok_and(|item| item.state.active));
+
+    let branch = active.or_else(|| {
+        children.iter().find(|child| {
+            fragments
+                .get(**child)
+                .is_ok_and(|item| item.eval.result == Some(true))
+        })
+    });
+
+    if let Some(branch) = branch {
+        descend_tree(*branch, evaluation, fragments, leaves, &mut None);
+    }
+}
+
+/// Walk only the first of the sequence's items that can begin.
+///
+/// Which items can begin is decided by the sequence itself when it's
+/// evaluated, so this only chooses between the optional items it left
+/// to their own evaluations. Active sequences and timelines are walked
+/// too, since their own items may overlap.
+fn descend_sequence(
+    children: &[Entity],
+    evaluation: Evaluation,
+    fragments: &Query<WalkData>,
+    leaves: &mut Vec<(Entity, Evaluation)>,
+) {
+    for child in children {
+        let Ok(item) = fragments.get(*child) else {
+            continue;
+        };
+
+        if is_active_concurrent(item.state, item.concurrent) {
+            descend_tree(*child, evaluation, fragments, leaves, &mut None);
+            continue;
+        }
+
+        if !item.state.active_events.is_empty() {
+            continue;
+        }
+
+        let can_begin = item.optional.is_none_or(OptionalItem::can_begin);
+        if !can_begin || item.eval.result == Some(false) {
+            continue;
+        }
+
+        descend_tree(*child, evaluation, fragments, leaves, &mut None);
+        break;
+    }
+}
+
 #[derive(Debug, Default, Resource)]
 pub struct SelectedFragments(pub Vec<Entity>);
 
+/// Determines how leaves are chosen once every tree has been walked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum SelectionMode {
+    /// Select the leaves with the lowest evaluation count.
+    #[default]
+    Count,
+
+    /// Select each tree's leaves with the highest cumulative score.
+    ///
+    /// Leaves without a score are treated as having a score of zero.
+    /// Scores are only compared within a tree, so every tree with a
+    /// playable leaf selects its best ones.
+    /// Put candidates in a [`best_of`] to have them compete.
+    ///
+    /// [`best_of`]: crate::combinators::best_of::best_of
+    Score,
+}
+
+/// Determines which trees are walked when selecting leaves.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum TreeWalk {
+    /// Only walk trees with a fragment whose evaluation,
+    /// state or children changed since the last walk.
+    #[default]
+    Incremental,
+
+    /// Walk every tree every frame.
+    ///
+    /// This is slower, but can be useful when debugging selection.
+    Full,
+}
+
 pub fn select_fragments(
-    roots: Query<(Entity, &Evaluation), With<Root>>,
-    fragments: Query<(
-        &Evaluation,
-        Option<&Children>,
-        Option<&Leaf>,
-        Option<&OrItem>,
-    )>,
+    mut roots: Query<(Entity, &Evaluation, &mut CachedLeaves), With<Root>>,
+    fragments: Query<WalkData>,
+    mode: Res<SelectionMode>,
+    walk: Res<TreeWalk>,
     mut selected_fragments: ResMut<SelectedFragments>,
 ) {
-    // traverse trees to build up full evaluatinos
+    // traverse changed trees to build up full evaluations
     let mut leaves = Vec::new();
+    selected_fragments.0.clear();
+
+    for (root, eval, mut cache) in roots.iter_mut() {
+        if cache.dirty || *walk == TreeWalk::Full {
+            let cache = cache.as_mut();
+            cache.leaves.clear();
+            cache.dirty = false;
+
+            let mut or = None;
+            descend_tree(root, *eval, &fragments, &mut cache.leaves, &mut or);
+        }
 
-    for (root, eval) in roots.iter() {
-        let mut or = None;
-        descend_tree(root, *eval, &fragments, &mut leaves, &mut or);
+        match *mode {
+            SelectionMode::Count => leaves.extend_from_slice(&cache.leaves),
+            // scores are compared within each tree
+            SelectionMode::Score => {
+                let best = cache
+                    .leaves
+                    .iter()
+                    .map(|(_, e)| e.score.unwrap_or_default())
+                    .fold(f32::NEG_INFINITY, f32::max);
+
+                selected_fragments.0.extend(
+                    cache
+                        .leaves
+                        .iter()
+                        .filter(|(_, e)| e.score.unwrap_or_default() == best)
+                        .map(|(e, _)| *e),
+                );
+            }
+        }
     }
 
     leaves.sort_by_key(|(_, e)| e.count);
 
-    selected_fragments.0.clear();
-
     if let Some((_, eval)) = leaves.first() {
         selected_fragments.0.extend(
             leaves
@@ -0,0 +1,381 @@
+//! Systems attached to individual fragments.
+//!
+//! Rather than registering a one-shot system for every fragment,
+//! fragment systems are grouped by type and run from a single
+//! scheduled system. Each group runs in parallel with every other
+//! system that doesn't conflict with it.
+
+use super::FragmentId;
+use crate::app::{self, EvaluateSets};
+use crate::evaluate::{Evaluate, Evaluation};
+use bevy_app::PreUpdate;
+use bevy_ecs::{
+    archetype::{ArchetypeComponentId, ArchetypeGeneration},
+    component::{ComponentId, Tick},
+    entity::EntityHashSet,
+    prelude::*,
+    query::{Access, QueryState, ROQueryItem, ReadOnlyQueryData},
+    schedule::InternedSystemSet,
+    system::{System, SystemIn, SystemInput, SystemParamValidationError},
+    world::{unsafe_world_cell::UnsafeWorldCell, DeferredWorld},
+};
+use bevy_log::prelude::*;
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+/// Determines when a group of fragment systems runs
+/// and what happens with their output.
+pub(crate) trait FragmentSystemKind<Out>: Send + Sync + 'static {
+    /// Data read from a fragment to decide whether its system should run.
+    type Gate: ReadOnlyQueryData + 'static;
+
+    /// The stage these systems run in.
+    const SET: EvaluateSets = EvaluateSets::Systems;
+
+    fn should_run(gate: ROQueryItem<'_, Self::Gate>) -> bool;
+
+    fn apply(world: &mut World, fragment: FragmentId, output: Out);
+}
+
+/// System inputs that can be provided to fragment systems.
+pub(crate) trait FragmentInput: SystemInput {
+    fn input(fragment: FragmentId) -> Self::Inner<'static>;
+}
+
+impl FragmentInput for () {
+    fn input(_: FragmentId) {}
+}
+
+impl FragmentInput for In<FragmentId> {
+    fn input(fragment: FragmentId) -> FragmentId {
+        fragment
+    }
+}
+
+type Instances<S> = Arc<Mutex<Vec<(Entity, S)>>>;
+
+/// The instances shared between a [`FragmentSystems`] and the world.
+#[derive(Resource)]
+struct FragmentSystemStore<S, K>(Instances<S>, PhantomData<fn() -> K>);
+
+/// Attach a system to a fragment.
+///
+/// Systems of the same type and kind are run together from a single
+/// system in [`SequenceSets::Evaluate`](crate::SequenceSets::Evaluate),
+/// which is inserted the first time a system of that type is attached.
+pub(crate) fn insert_fragment_system<K, S, I, O, M>(
+    commands: &mut Commands,
+    fragment: FragmentId,
+    system: S,
+) where
+    K: FragmentSystemKind<O>,
+    S: IntoSystem<I, O, M> + Send + 'static,
+    I: FragmentInput,
+    O: Send + Sync + 'static,
+{
+    commands.queue(move |world: &mut World| {
+        let mut system = IntoSystem::into_system(system);
+        system.initialize(world);
+
+        let store = match world.get_resource::<FragmentSystemStore<S::System, K>>() {
+            Some(store) => store.0.clone(),
+            None => {
+                let store = Instances::<S::System>::default();
+                world.insert_resource(FragmentSystemStore::<S::System, K>(
+                    store.clone(),
+                    PhantomData,
+                ));
+
+                let systems = FragmentSystems::<S::System, K>::new(&system, store.clone());
+                app::insert_systems(world, PreUpdate, K::SET, systems);
+
+                store
+            }
+        };
+
+        store.lock().unwrap().push((fragment.entity(), system));
+    });
+}
+
+/// Evaluate a fragment whose system couldn't run to false.
+fn reject(world: &mut World, fragment: FragmentId) {
+    if let Some(mut evaluation) = world.get_mut::<Evaluation>(fragment.entity()) {
+        evaluation.merge(false.evaluate());
+    }
+}
+
+/// Runs every fragment's instance of `S`.
+pub(crate) struct FragmentSystems<S: System, K: FragmentSystemKind<S::Out>> {
+    name: Cow<'static, str>,
+    instances: Instances<S>,
+    gate: Option<QueryState<K::Gate>>,
+    component_access: Access<ComponentId>,
+    archetype_component_access: Access<ArchetypeComponentId>,
+    archetype_generation: ArchetypeGeneration,
+    is_send: bool,
+    is_exclusive: bool,
+    outputs: Vec<(FragmentId, S::Out)>,
+    /// Fragments whose instance failed parameter validation this run.
+    invalid: Vec<FragmentId>,
+    /// Fragments whose validation failure has already been logged.
+    warned: EntityHashSet,
+    last_run: Tick,
+}
+
+impl<S, I, K> FragmentSystems<S, K>
+where
+    S: System<In = I>,
+    I: FragmentInput,
+    K: FragmentSystemKind<S::Out>,
+{
+    /// Create a system that runs instances of `S`.
+    ///
+    /// All instances of `S` share the same component access,
+    /// so `prototype` is only used to determine it.
+    fn new(prototype: &S, instances: Instances<S>) -> Self {
+        Self {
+            name: format!("bevy_sequence::fragment_systems<{}>", prototype.name()).into(),
+            instances,
+            gate: None,
+            component_access: prototype.component_access().clone(),
+            archetype_component_access: Default::default(),
+            archetype_generation: ArchetypeGeneration::initial(),
+            is_send: prototype.is_send(),
+            is_exclusive: prototype.is_exclusive(),
+            outputs: Vec::new(),
+            invalid: Vec::new(),
+            warned: EntityHashSet::default(),
+            last_run: Tick::new(0),
+        }
+    }
+
+    /// Run each instance whose fragment passes the gate.
+    ///
+    /// Instances whose fragments no longer exist are dropped, and
+    /// fragments whose instance can't run are evaluated to false.
+    fn run_instances(
+        &mut self,
+        instances: &mut Vec<(Entity, S)>,
+        world: UnsafeWorldCell,
+        mut run: impl FnMut(&mut S, SystemIn<'_, S>) -> Result<S::Out, SystemParamValidationError>,
+    ) {
+        let this_run = world.change_tick();
+
+        // the gate is dropped before any instance runs, since exclusive
+        // instances take the whole world
+        let gated: Vec<_> = {
+            let gate = self
+                .gate
+                .as_ref()
+                .expect("fragment systems not initialized");
+            // SAFETY: the gate's access was added to this system's access when it was initialized.
+            let gate =
+                unsafe { gate.query_unchecked_manual_with_ticks(world, self.last_run, this_run) };
+
+            instances
+                .retain(|(entity, _)| gate.contains(*entity) || world.entities().contains(*entity));
+            instances
+                .iter()
+                .map(|(entity, _)| gate.get(*entity).is_ok_and(|item| K::should_run(item)))
+                .collect()
+        };
+
+        for ((entity, system), should_run) in instances.iter_mut().zip(gated) {
+            if !should_run {
+                continue;
+            }
+
+            let fragment = FragmentId::new(*entity);
+            match run(system, I::input(fragment)) {
+                Ok(output) => self.outputs.push((fragment, output)),
+                Err(err) => {
+                    if self.warned.insert(*entity) {
+                        warn!(
+                            "{} could not run for {}: {err}",
+                            system.name(),
+                            fragment.entity()
+                        );
+                    }
+                    self.invalid.push(fragment);
+                }
+            }
+        }
+
+        self.last_run = this_run;
+    }
+
+    /// Take the instances out of the store so systems that
+    /// spawn fragments can't deadlock.
+    fn take_instances(&self) -> Vec<(Entity, S)> {
+        core::mem::take(&mut *self.instances.lock().unwrap())
+    }
+
+    /// Return the instances to the store, keeping any that were added in the meantime.
+    fn return_instances(&self, mut instances: Vec<(Entity, S)>) {
+        let mut store = self.instances.lock().unwrap();
+        instances.append(&mut store);
+        *store = instances;
+    }
+}
+
+impl<S, I, K> System for FragmentSystems<S, K>
+where
+    S: System<In = I>,
+    I: FragmentInput,
+    S::Out: Send + Sync,
+    K: FragmentSystemKind<S::Out>,
+{
+    type In = ();
+    type Out = ();
+
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+
+    fn component_access(&self) -> &Access<ComponentId> {
+        &self.component_access
+    }
+
+    fn archetype_component_access(&self) -> &Access<ArchetypeComponentId> {
+        &self.archetype_component_access
+    }
+
+    fn is_send(&self) -> bool {
+        self.is_send
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.is_exclusive
+    }
+
+    fn has_deferred(&self) -> bool {
+        true
+    }
+
+    unsafe fn run_unsafe(&mut self, _: (), world: UnsafeWorldCell) {
+        let mut instances = self.take_instances();
+        self.run_instances(&mut instances, world, |system, input| {
+            // SAFETY: every instance shares the access declared by this system,
+            // and `update_archetype_component_access` has been called for each instance.
+            unsafe {
+                system.validate_param_unsafe(world)?;
+                Ok(system.run_unsafe(input, world))
+            }
+        });
+        self.return_instances(instances);
+    }
+
+    fn run(&mut self, _: (), world: &mut World) {
+        self.update_archetype_component_access(world.as_unsafe_world_cell_readonly());
+        if !self.is_exclusive {
+            // SAFETY: we have exclusive access to the world.
+            unsafe { self.run_unsafe((), world.as_unsafe_world_cell()) };
+            self.apply_deferred(world);
+            return;
+        }
+
+        let mut instances = self.take_instances();
+        let cell = world.as_unsafe_world_cell();
+        self.run_instances(&mut instances, cell, |system, input| {
+            // SAFETY: exclusive systems are never run in parallel, and the gate
+            // is no longer borrowed, so nothing else can access the world
+            // while this instance runs.
+            let world = unsafe { cell.world_mut() };
+            system.validate_param(world)?;
+            Ok(system.run(input, world))
+        });
+        self.return_instances(instances);
+        self.apply_deferred(world);
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) {
+        let mut instances = self.take_instances();
+        for (_, system) in instances.iter_mut() {
+            system.apply_deferred(world);
+        }
+        self.return_instances(instances);
+
+        for (fragment, output) in self.outputs.drain(..) {
+            K::apply(world, fragment, output);
+        }
+        for fragment in self.invalid.drain(..) {
+            reject(world, fragment);
+        }
+    }
+
+    fn queue_deferred(&mut self, mut world: DeferredWorld) {
+        let mut instances = self.take_instances();
+        for (_, system) in instances.iter_mut() {
+            system.queue_deferred(world.reborrow());
+        }
+        self.return_instances(instances);
+
+        let outputs: Vec<_> = self.outputs.drain(..).collect();
+        let invalid: Vec<_> = self.invalid.drain(..).collect();
+        world.commands().queue(move |world: &mut World| {
+            for (fragment, output) in outputs {
+                K::apply(world, fragment, output);
+            }
+            for fragment in invalid {
+                reject(world, fragment);
+            }
+        });
+    }
+
+    unsafe fn validate_param_unsafe(
+        &mut self,
+        _: UnsafeWorldCell,
+    ) -> Result<(), SystemParamValidationError> {
+        // Each instance is validated just before it runs.
+        Ok(())
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        let gate = QueryState::<K::Gate>::new(world);
+        self.component_access
+            .extend(gate.component_access().access());
+        self.gate = Some(gate);
+    }
+
+    fn update_archetype_component_access(&mut self, world: UnsafeWorldCell) {
+        let gate = self
+            .gate
+            .as_mut()
+            .expect("fragment systems not initialized");
+        let archetypes = world.archetypes();
+        let old_generation =
+            core::mem::replace(&mut self.archetype_generation, archetypes.generation());
+
+        for archetype in &archetypes[old_generation..] {
+            // SAFETY: the gate was initialized from this world.
+            unsafe { gate.new_archetype(archetype, &mut self.archetype_component_access) };
+        }
+
+        for (_, system) in self.instances.lock().unwrap().iter_mut() {
+            system.update_archetype_component_access(world);
+            self.archetype_component_access
+                .extend(system.archetype_component_access());
+        }
+    }
+
+    fn check_change_tick(&mut self, change_tick: Tick) {
+        for (_, system) in self.instances.lock().unwrap().iter_mut() {
+            system.check_change_tick(change_tick);
+        }
+    }
+
+    fn default_system_sets(&self) -> Vec<InternedSystemSet> {
+        Vec::new()
+    }
+
+    fn get_last_run(&self) -> Tick {
+        self.last_run
+    }
+
+    fn set_last_run(&mut self, last_run: Tick) {
+        self.last_run = last_run;
+    }
+}
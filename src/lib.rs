@@ -3,7 +3,7 @@
 //! `bevy_sequence` is concise because you can define
 //! sequences with minimal syntax.
 //!
-//! ```
+//! ```ignore
 //! (
 //!     "Hello, Alice!",
 //!     "Hey Bob...",
@@ -14,7 +14,7 @@
 //! It's also expressive because of its
 //! rich set of combinators.
 //!
-//! ```
+//! ```ignore
 //! (
 //!     // Play a sound
 //!     "Hello, Alice!".sound("hello.ogg"),
@@ -30,7 +30,7 @@
 //! With a little setup, sequences of heterogenous types know
 //! how to spawn themselves.
 //!
-//! ```
+//! ```ignore
 //! struct MyData(Cow<'static, str>);
 //!
 //! fn system(mut commands: Commands) {
@@ -47,26 +47,46 @@
 #![allow(clippy::type_complexity)]
 
 pub mod app;
+pub mod clock;
 pub mod combinators;
+pub mod conditions;
 pub mod evaluate;
 pub mod fragment;
 
+#[cfg(test)]
+mod test_app;
+
 pub use crate::app::{SequencePlugin, SequenceSets};
 
 pub mod prelude {
-    pub use crate::{SequencePlugin, SequenceSets};
+    pub use crate::{clock::SequenceClock, SequencePlugin, SequenceSets};
+
+    pub use crate::conditions::*;
 
-    pub use crate::evaluate::{Evaluate, Evaluation};
+    pub use crate::evaluate::{Evaluate, Evaluation, EvaluationWriter};
 
     pub use crate::fragment::{
         spawn_root, spawn_root_with, Context, Fragment, FragmentId, FragmentState, IntoFragment,
+        SelectionMode, TreeWalk,
     };
 
     pub use crate::fragment::event::{EventId, FragmentEndEvent, FragmentEvent, IdPair};
 
     pub use crate::combinators::{
-        //distribution::{choice, distribution},
-        select::select,
+        best_of::best_of,
+        call::{call, SequenceRegistry},
+        cond::{cond, otherwise},
+        cycle::cycle,
+        distribution::{choice, distribution, distribution_with},
+        first_available::first_available,
+        label::goto,
+        limit::{reset_limit, LimitMode},
+        select::{select, select_by, SelectMode},
+        sequence::sequence_overlap,
+        shuffle::shuffle,
+        signal::{barrier, emit_signal, wait_signal, Signals},
+        state_machine::{machine_state, state_machine},
+        timeline::{seek_timeline, timeline},
         FragmentExt,
     };
 
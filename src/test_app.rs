@@ -0,0 +1,64 @@
+//! A minimal app for testing how trees play.
+
+use crate::fragment::DataLeaf;
+use crate::prelude::*;
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use bevy_time::{TimePlugin, TimeUpdateStrategy};
+use std::time::Duration;
+
+/// The time each frame advances by.
+pub(crate) const FRAME: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+pub(crate) struct Line(&'static str);
+
+impl IntoFragment<Line> for &'static str {
+    fn into_fragment(self, context: &Context, commands: &mut Commands) -> FragmentId {
+        <_ as IntoFragment<Line>>::into_fragment(DataLeaf::new(Line(self)), context, commands)
+    }
+}
+
+/// Every line that began, in order.
+#[derive(Debug, Default, Resource)]
+pub(crate) struct Played(pub Vec<&'static str>);
+
+/// An app that ends each line in the frame it begins.
+pub(crate) fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((TimePlugin, SequencePlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .init_resource::<Played>()
+        .add_systems(Update, play);
+
+    app
+}
+
+/// Spawn a root in the app.
+pub(crate) fn spawn(app: &mut App, fragment: impl IntoFragment<Line>) -> FragmentId {
+    let world = app.world_mut();
+    let id = spawn_root(fragment, &mut world.commands());
+    world.flush();
+
+    id
+}
+
+/// Run `frames` frames and return every line played so far.
+pub(crate) fn run(app: &mut App, frames: usize) -> &[&'static str] {
+    for _ in 0..frames {
+        app.update();
+    }
+
+    &app.world().resource::<Played>().0
+}
+
+fn play(
+    mut reader: EventReader<FragmentEvent<Line>>,
+    mut writer: EventWriter<FragmentEndEvent>,
+    mut played: ResMut<Played>,
+) {
+    for event in reader.read() {
+        played.0.push(event.data.0);
+        writer.write(event.end());
+    }
+}